@@ -0,0 +1,366 @@
+use std::{ops::Range, time::Duration};
+
+use bevy::{audio::AudioSink, prelude::*};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{save::Settings, AppState};
+
+// 声音插件，负责音量和静音
+pub struct AudioPlugin;
+
+// 音量设置，保存在 Settings 中
+// 静音只在本次运行中有效，不保存
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub music_volume: f32, // 音乐音量，0.0 ~ 1.0
+    pub sfx_volume: f32,   // 音效音量，0.0 ~ 1.0
+    #[serde(skip)]
+    pub muted: bool, // 是否静音
+}
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+        }
+    }
+}
+impl AudioSettings {
+    // 实际播放音乐时使用的音量
+    pub fn music(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.music_volume
+        }
+    }
+
+    // 实际播放音效时使用的音量
+    pub fn sfx(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.sfx_volume
+        }
+    }
+}
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicController>()
+            .add_event::<PlaySfx>()
+            .add_system(play_queued_sfx)
+            .add_system(toggle_mute)
+            .add_system(update_music)
+            // 暂停时音乐也暂停，继续或放弃本局时恢复
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(pause_music))
+            .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(resume_music));
+    }
+}
+
+// 随机音调的范围，避免重复的音效听起来太单调
+pub const PITCH_VARIATION: Range<f32> = 0.9..1.1;
+
+// 排队等待播放的音效，由 play_queued_sfx 统一交给 Audio 播放
+pub struct PlaySfx {
+    pub source: Handle<AudioSource>,
+    pub volume: f32, // 相对于音效音量的比例
+    pub speed: f32,  // 播放速度（音调）
+}
+
+// 所有音效都经由这里排队播放
+pub fn play_sfx(sfx: &mut EventWriter<PlaySfx>, source: Handle<AudioSource>) {
+    play_sfx_with_speed(sfx, source, 1.);
+}
+
+// 以随机的音调播放音效，rng 为 GameRng 的 Pitch 子流
+pub fn play_sfx_varied(
+    sfx: &mut EventWriter<PlaySfx>,
+    source: Handle<AudioSource>,
+    rng: &mut impl Rng,
+) {
+    let speed = rng.gen_range(PITCH_VARIATION);
+    play_sfx_with_speed(sfx, source, speed);
+}
+
+// 以较小的音量和随机的音调播放音效，volume 为相对于音效音量的比例
+pub fn play_sfx_quiet(
+    sfx: &mut EventWriter<PlaySfx>,
+    source: Handle<AudioSource>,
+    volume: f32,
+    rng: &mut impl Rng,
+) {
+    let speed = rng.gen_range(PITCH_VARIATION);
+    play_sfx_scaled(sfx, source, volume, speed);
+}
+
+// 以指定的播放速度（音调）播放音效
+pub fn play_sfx_with_speed(
+    sfx: &mut EventWriter<PlaySfx>,
+    source: Handle<AudioSource>,
+    speed: f32,
+) {
+    play_sfx_scaled(sfx, source, 1., speed);
+}
+
+// 以相对于音效音量的比例和指定的播放速度播放音效
+pub fn play_sfx_scaled(
+    sfx: &mut EventWriter<PlaySfx>,
+    source: Handle<AudioSource>,
+    volume: f32,
+    speed: f32,
+) {
+    sfx.send(PlaySfx {
+        source,
+        volume,
+        speed,
+    });
+}
+
+// 播放排队的音效，静音时直接跳过
+fn play_queued_sfx(audio: Res<Audio>, settings: Res<Settings>, mut events: EventReader<PlaySfx>) {
+    for sfx in events.iter() {
+        if settings.audio.sfx() <= 0. {
+            continue;
+        }
+
+        audio.play_with_settings(
+            sfx.source.clone(),
+            PlaybackSettings::ONCE
+                .with_volume(settings.audio.sfx() * sfx.volume)
+                .with_speed(sfx.speed),
+        );
+    }
+}
+
+// 输入字母时的旋律：字母按在字母表中的位置对应五声音阶上的一个音，两个八度循环
+// 数字 0~9 与字母 a~j 相同，其它字符保持原来的音调
+const MELODY_SCALE: [f32; 5] = [0., 2., 4., 7., 9.];
+const MELODY_BASE_SPEED: f32 = 0.6;
+
+pub fn note_speed(c: char) -> f32 {
+    let index = match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => c as u32 - 'a' as u32,
+        c @ '0'..='9' => c as u32 - '0' as u32,
+        _ => return 1.,
+    } as usize
+        % (MELODY_SCALE.len() * 2);
+
+    let octave = (index / MELODY_SCALE.len()) as f32;
+    let semitones = MELODY_SCALE[index % MELODY_SCALE.len()] + 12. * octave;
+    MELODY_BASE_SPEED * 2f32.powf(semitones / 12.)
+}
+
+// 切换背景音乐时交叉淡入淡出的时长
+pub const MUSIC_FADE_SECONDS: f32 = 0.5;
+
+// 背景音乐控制
+// bevy::audio::AudioSink 用于控制声音资源
+// 切换音乐时旧的音乐逐渐淡出，新的音乐逐渐淡入
+// 刚开始播放的音乐要等到下一帧才会出现在 Assets<AudioSink> 中，
+// 所以要停止的句柄先记在 stopping 里，等 sink 出现后再停止，快速切换状态时不会留下还在播放的音乐
+pub struct MusicController {
+    current: Option<Handle<AudioSink>>,  // 当前（淡入中）的音乐
+    previous: Option<Handle<AudioSink>>, // 淡出中的音乐
+    stopping: Vec<Handle<AudioSink>>,    // 等待停止的音乐
+    fade: Timer,
+}
+impl Default for MusicController {
+    fn default() -> Self {
+        let mut fade = Timer::from_seconds(MUSIC_FADE_SECONDS, false);
+        fade.tick(fade.duration());
+
+        Self {
+            current: None,
+            previous: None,
+            stopping: Vec::new(),
+            fade,
+        }
+    }
+}
+
+// 音乐控制用到的 AudioSink 方法，测试中用假的 sink 代替
+trait MusicSink {
+    fn set_volume(&self, volume: f32);
+    fn stop(&self);
+}
+impl MusicSink for AudioSink {
+    fn set_volume(&self, volume: f32) {
+        AudioSink::set_volume(self, volume);
+    }
+    fn stop(&self) {
+        AudioSink::stop(self);
+    }
+}
+
+impl MusicController {
+    // 开始循环播放新的音乐，并淡出当前的音乐
+    pub fn play(
+        &mut self,
+        audio: &Audio,
+        audio_sinks: &Assets<AudioSink>,
+        source: Handle<AudioSource>,
+    ) {
+        // 从音量 0 开始淡入；即使静音也要播放，以便取消静音后继续
+        let handle = audio.play_with_settings(source, PlaybackSettings::LOOP.with_volume(0.));
+        let handle = audio_sinks.get_handle(handle);
+        self.start(handle, |h| audio_sinks.get(h));
+    }
+
+    fn start<'a, S: MusicSink + 'a>(
+        &mut self,
+        handle: Handle<AudioSink>,
+        sinks: impl Fn(&Handle<AudioSink>) -> Option<&'a S>,
+    ) {
+        // 上一次的淡出还没有结束，直接停止，避免多首音乐叠在一起
+        self.stopping.extend(self.previous.take());
+        self.previous = self.current.replace(handle);
+        self.fade.reset();
+        self.stop_pending(&sinks);
+    }
+
+    // 停止所有已经出现的等待停止的音乐，还没出现的留到以后
+    fn stop_pending<'a, S: MusicSink + 'a>(
+        &mut self,
+        sinks: &impl Fn(&Handle<AudioSink>) -> Option<&'a S>,
+    ) {
+        self.stopping.retain(|h| match sinks(h) {
+            Some(sink) => {
+                sink.stop();
+                false
+            }
+            None => true,
+        });
+    }
+
+    // 推进淡入淡出，volume 为设置中的音乐音量
+    fn update<'a, S: MusicSink + 'a>(
+        &mut self,
+        delta: Duration,
+        volume: f32,
+        sinks: impl Fn(&Handle<AudioSink>) -> Option<&'a S>,
+    ) {
+        self.stop_pending(&sinks);
+
+        self.fade.tick(delta);
+        let t = self.fade.percent();
+
+        if let Some(sink) = self.current.as_ref().and_then(&sinks) {
+            sink.set_volume(volume * t);
+        }
+
+        if self.fade.finished() {
+            // 淡出结束，交给 stopping 停止；sink 还没出现的话以后再停
+            self.stopping.extend(self.previous.take());
+            self.stop_pending(&sinks);
+        } else if let Some(sink) = self.previous.as_ref().and_then(&sinks) {
+            sink.set_volume(volume * (1. - t));
+        }
+    }
+
+    // 暂停或继续播放当前和淡出中的音乐
+    pub fn set_paused(&self, audio_sinks: &Assets<AudioSink>, paused: bool) {
+        for handle in [&self.current, &self.previous].into_iter().flatten() {
+            if let Some(sink) = audio_sinks.get(handle) {
+                if paused {
+                    sink.pause();
+                } else {
+                    sink.play();
+                }
+            }
+        }
+    }
+}
+
+fn pause_music(controller: Res<MusicController>, audio_sinks: Res<Assets<AudioSink>>) {
+    controller.set_paused(&audio_sinks, true);
+}
+
+fn resume_music(controller: Res<MusicController>, audio_sinks: Res<Assets<AudioSink>>) {
+    controller.set_paused(&audio_sinks, false);
+}
+
+// 按 M 键切换静音
+// 游戏中字母 m 是打字内容的一部分，所以只在开始、结束屏幕生效
+fn toggle_mute(
+    keyboard: Res<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    mut settings: ResMut<Settings>,
+) {
+    if !keyboard.just_pressed(KeyCode::M) {
+        return;
+    }
+    if !matches!(state.current(), AppState::StartScreen | AppState::EndScreen) {
+        return;
+    }
+
+    settings.audio.muted = !settings.audio.muted;
+}
+
+// 推进音乐的淡入淡出，并让音乐音量跟随设置
+fn update_music(
+    mut controller: ResMut<MusicController>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    controller.update(time.delta(), settings.audio.music(), |h| audio_sinks.get(h));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use bevy::{asset::HandleId, utils::HashMap};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeSink {
+        volume: Cell<f32>,
+        stopped: Cell<bool>,
+    }
+    impl MusicSink for FakeSink {
+        fn set_volume(&self, volume: f32) {
+            self.volume.set(volume);
+        }
+        fn stop(&self) {
+            self.stopped.set(true);
+        }
+    }
+
+    #[test]
+    fn only_one_loop_survives_rapid_transitions() {
+        let mut controller = MusicController::default();
+        let mut sinks: HashMap<Handle<AudioSink>, FakeSink> = HashMap::default();
+        let frame = Duration::from_millis(16);
+
+        // 连续快速切换状态，每次新音乐的 sink 都要过几帧才出现
+        let mut pending = Vec::new();
+        for i in 0..50 {
+            let handle = Handle::weak(HandleId::random::<AudioSink>());
+            controller.start(handle.clone(), |h| sinks.get(h));
+            pending.push(handle);
+            if i % 3 == 0 {
+                for handle in pending.drain(..) {
+                    sinks.insert(handle, FakeSink::default());
+                }
+            }
+            controller.update(frame, 1., |h| sinks.get(h));
+        }
+        for handle in pending.drain(..) {
+            sinks.insert(handle, FakeSink::default());
+        }
+
+        // 淡入淡出结束后只剩一首音乐在播放
+        for _ in 0..100 {
+            controller.update(frame, 1., |h| sinks.get(h));
+        }
+        let playing: Vec<_> = sinks.values().filter(|s| !s.stopped.get()).collect();
+        assert_eq!(playing.len(), 1);
+        assert_eq!(playing[0].volume.get(), 1.);
+    }
+}
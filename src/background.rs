@@ -0,0 +1,146 @@
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+
+use crate::{AppState, ScrollDir, Speed, TextureAssets, TimeScale};
+
+// 远景插件：障碍物后面的山丘和云朵，以较慢的速度滚动产生纵深感
+pub struct BackgroundPlugin;
+
+// 远景层，数值为相对于地面的滚动速度比例
+#[derive(Component, Clone, Copy)]
+pub struct Background(f32);
+
+// 每块远景的宽度，与地面块一样首尾相接
+const BACKGROUND_LENGTH: f32 = 60.;
+
+// 一个远景层
+struct Layer {
+    speed: f32, // 滚动速度比例
+    y: f32,     // 中心高度
+    z: f32,     // 深度，在障碍物和地面之后
+    height: f32,
+}
+
+// 山丘贴着地面的远端，云朵在更远的地方
+const HILLS: Layer = Layer {
+    speed: 0.3,
+    y: 2.5,
+    z: -19.5,
+    height: 5.,
+};
+const CLOUDS: Layer = Layer {
+    speed: 0.1,
+    y: 9.5,
+    z: -22.,
+    height: 5.,
+};
+
+// 远景共用的网格和材质
+struct BackgroundAssets {
+    mesh: Handle<Mesh>,
+    hills: Handle<StandardMaterial>,
+    clouds: Handle<StandardMaterial>,
+}
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(background_movement.label("background_movement")),
+            )
+            // 重置时远景会被销毁，在任何状态下都重新生成
+            .add_system(spawn_background.after("background_movement"));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    texture_assets: Res<TextureAssets>,
+) {
+    let material = |texture: &Handle<Image>| StandardMaterial {
+        base_color_texture: Some(texture.clone()),
+        alpha_mode: AlphaMode::Blend,
+        perceptual_roughness: 1.,
+        ..Default::default()
+    };
+
+    // 高度为 1 的长条，各层通过缩放设置实际高度
+    let quad = shape::Quad::new(Vec2::new(BACKGROUND_LENGTH, 1.));
+
+    commands.insert_resource(BackgroundAssets {
+        mesh: meshes.add(Mesh::from(quad)),
+        hills: materials.add(material(&texture_assets.hills)),
+        clouds: materials.add(material(&texture_assets.clouds)),
+    });
+}
+
+// 移动远景，与 ground_movement 相同，只是速度按比例减慢
+fn background_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &Background)>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    speed: Res<Speed>,
+    dir: Res<ScrollDir>,
+) {
+    let delta = time_scale.delta_seconds(&time) * speed.current;
+
+    for (entity, mut transform, background) in query.iter_mut() {
+        transform.translation.x -= delta * background.0 * dir.0;
+        // 移出屏幕后销毁，由 spawn_background 在后面补上
+        if transform.translation.x * dir.0 < -BACKGROUND_LENGTH {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// 每一层始终保持两块远景
+fn spawn_background(
+    mut commands: Commands,
+    assets: Option<Res<BackgroundAssets>>,
+    query: Query<(&Transform, &Background)>,
+    dir: Res<ScrollDir>,
+) {
+    let assets = match assets {
+        Some(assets) => assets,
+        None => return,
+    };
+
+    for (layer, material) in [(HILLS, &assets.hills), (CLOUDS, &assets.clouds)] {
+        let xs = query
+            .iter()
+            .filter(|(_, background)| background.0 == layer.speed)
+            .map(|(transform, _)| transform.translation.x * dir.0)
+            .collect::<Vec<_>>();
+
+        // 第一块从原点开始，之后接在最前面一块的后面，镜像模式下按翻转前的坐标计算
+        let mut next_x = xs
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .map_or(0., |x| x + BACKGROUND_LENGTH);
+
+        let scale = Vec3::new(1., layer.height, 1.);
+
+        for _ in xs.len()..2 {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(next_x * dir.0, layer.y, layer.z)
+                        .with_scale(scale),
+                    ..Default::default()
+                })
+                .insert(Background(layer.speed))
+                .insert(NotShadowCaster)
+                .insert(NotShadowReceiver);
+
+            next_x += BACKGROUND_LENGTH;
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    luck::{GameRng, RngStream},
+    ScrollDir,
+};
+
+// 摄像机插件，负责撞击时的镜头抖动
+pub struct CameraPlugin;
+
+// 摄像机的初始位置，抖动结束后恢复到这里
+// 摄像机略微偏向鸟的前方，镜像模式下偏向另一边
+pub fn camera_transform(dir: &ScrollDir) -> Transform {
+    Transform::from_xyz(4.5 * dir.0, 5.8, 11.7).with_rotation(Quat::from_rotation_x(-0.211))
+}
+
+// 镜头抖动
+pub struct CameraShake {
+    timer: Timer,
+    // 抖动幅度，随时间逐渐衰减到 0
+    pub intensity: f32,
+}
+impl Default for CameraShake {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(0.3, false);
+        // 初始状态为已结束，不抖动
+        timer.tick(timer.duration());
+
+        Self {
+            timer,
+            intensity: 0.15,
+        }
+    }
+}
+impl CameraShake {
+    pub fn start(&mut self) {
+        self.timer.reset();
+    }
+}
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraShake>().add_system(camera_shake);
+    }
+}
+
+fn camera_shake(
+    mut shake: ResMut<CameraShake>,
+    mut query: Query<&mut Transform, With<Camera3d>>,
+    time: Res<Time>,
+    mut game_rng: ResMut<GameRng>,
+    dir: Res<ScrollDir>,
+) {
+    if shake.timer.finished() {
+        return;
+    }
+
+    shake.timer.tick(time.delta());
+
+    let base = camera_transform(&dir);
+
+    for mut transform in query.iter_mut() {
+        if shake.timer.finished() {
+            transform.translation = base.translation;
+            continue;
+        }
+
+        let falloff = 1. - shake.timer.percent();
+        let rng = game_rng.stream(RngStream::CameraShake);
+        let offset = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.);
+
+        transform.translation = base.translation + offset * shake.intensity * falloff;
+    }
+}
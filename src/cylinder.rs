@@ -43,8 +43,10 @@ impl From<Cylinder> for Mesh {
 
         let num_rings = c.segments + 1;
         let num_vertices = c.resolution * 2 + num_rings * (c.resolution + 1);
-        let num_faces = c.resolution * (num_rings - 2);
-        let num_indices = (2 * num_faces + 2 * (c.resolution - 1) * 2) * 3;
+        // 侧面每段每个切片两个三角形，上下盖子各 resolution - 2 个三角形
+        let num_barrel_triangles = 2 * c.resolution * c.segments;
+        let num_cap_triangles = 2 * (c.resolution - 2);
+        let num_indices = (num_barrel_triangles + num_cap_triangles) * 3;
 
         let mut positions = Vec::with_capacity(num_vertices as usize);
         let mut normals = Vec::with_capacity(num_vertices as usize);
@@ -65,9 +67,12 @@ impl From<Cylinder> for Mesh {
 
                 positions.push([c.radius * cos, y, c.radius * sin]);
                 normals.push([cos, 0., sin]);
+                // 每个环的首尾是两个位置相同的顶点，u 分别为 1 和 0，纹理绕一圈后无缝衔接
+                // 从外面看 theta 增大的方向是向左的，所以 u 要反过来，否则纹理是镜像的
+                // 纹理的 v 轴向下，底部的环 v 为 1
                 uvs.push([
-                    segment as f32 / c.resolution as f32,
-                    ring as f32 / c.segments as f32,
+                    1. - segment as f32 / c.resolution as f32,
+                    1. - ring as f32 / c.segments as f32,
                 ]);
             }
         }
@@ -106,10 +111,14 @@ impl From<Cylinder> for Mesh {
 
                 positions.push([cos * c.radius, y, sin * c.radius]);
                 normals.push([0.0, normal_y, 0.0]);
-                uvs.push([0.5 * (cos + 1.0), 1.0 - 0.5 * (sin + 1.0)]);
+                // 把圆盘映射到纹理中心的圆上，从外面看不是镜像的
+                let u = 0.5 * (cos + 1.0);
+                let v = 0.5 * (sin + 1.0);
+                uvs.push(if top { [u, v] } else { [u, 1.0 - v] });
             }
 
-            for i in 1..(c.resolution as u32 - 1) {
+            // 以第一个顶点为中心的扇形三角形，i 从 1 到 resolution - 2，共 resolution - 2 个
+            for i in 1..(c.resolution - 1) {
                 indices.extend_from_slice(&[
                     offset,
                     offset + i + winding.0,
@@ -132,3 +141,61 @@ impl From<Cylinder> for Mesh {
         mesh
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    #[test]
+    fn uv_count_matches_position_count() {
+        let mesh: Mesh = Cylinder {
+            radius: 0.75,
+            height: 3.,
+            resolution: 16,
+            segments: 4,
+        }
+        .into();
+
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
+        let uvs = mesh.attribute(Mesh::ATTRIBUTE_UV_0).unwrap().len();
+        assert_eq!(positions, uvs);
+
+        // 所有 UV 都在纹理范围内
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => {
+                assert!(uvs
+                    .iter()
+                    .all(|[u, v]| (0.0..=1.0).contains(u) && (0.0..=1.0).contains(v)));
+            }
+            _ => panic!("expected Float32x2 uvs"),
+        }
+    }
+
+    #[test]
+    fn indices_in_range() {
+        for resolution in [3, 4, 16, 64] {
+            let mesh: Mesh = Cylinder {
+                resolution,
+                ..Default::default()
+            }
+            .into();
+
+            let num_positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
+            let indices: Vec<usize> = mesh.indices().unwrap().iter().collect();
+
+            assert_eq!(indices.len() % 3, 0);
+            assert!(
+                indices.iter().all(|&i| i < num_positions),
+                "out-of-range index at resolution {}",
+                resolution
+            );
+
+            // 侧面加上下盖子的三角形数量
+            let segments = Cylinder::default().segments as usize;
+            let resolution = resolution as usize;
+            let triangles = 2 * resolution * segments + 2 * (resolution - 2);
+            assert_eq!(indices.len(), triangles * 3);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    luck::{GameRng, NextGapBag, RngStream},
+    save::Settings,
+    Action, AppState, FlapStep, GapSize, Gravity, ObstacleSpacing, Speed, SpeedConfig,
+    BIRB_START_Y,
+};
+
+// 难度插件：在开始屏幕选择难度，进入游戏时应用
+pub struct DifficultyPlugin;
+
+// 难度，默认为 Normal，保存在 Settings 中
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    // 在开始屏幕上输入的单词
+    pub fn word(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // 最高速度
+    pub fn max_speed(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 3.6,
+            Difficulty::Normal => 4.4,
+            Difficulty::Hard => 5.2,
+        }
+    }
+
+    // 障碍物之间的距离
+    pub fn obstacle_spacing(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 14.,
+            Difficulty::Normal => 12.,
+            Difficulty::Hard => 10.,
+        }
+    }
+
+    // 每次拍打翅膀移动的距离，简单难度下输入更少的字母就能飞到空隙
+    pub fn flap_step(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.3,
+            Difficulty::Normal => 0.25,
+            Difficulty::Hard => 0.25,
+        }
+    }
+
+    // 鸟每秒下沉的距离，只有困难难度会下沉，需要不断输入上方的单词
+    pub fn gravity(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.,
+            Difficulty::Normal => 0.,
+            Difficulty::Hard => 0.2,
+        }
+    }
+
+    // 上下障碍物之间空隙的大小
+    pub fn gap_size(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 2.4,
+            Difficulty::Normal => 2.,
+            Difficulty::Hard => 1.7,
+        }
+    }
+
+    // 分数达到 MOVING_OBSTACLE_MIN_SCORE 后，每个障碍物上下移动的概率
+    pub fn moving_obstacle_chance(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.15,
+            Difficulty::Normal => 0.25,
+            Difficulty::Hard => 0.4,
+        }
+    }
+
+    // 开启单词倒计时后，输入一个单词的限时（秒），单词越长时间越多
+    pub fn word_seconds(&self, len: usize) -> f32 {
+        let (base, per_letter) = match self {
+            Difficulty::Easy => (3., 1.),
+            Difficulty::Normal => (2.5, 0.8),
+            Difficulty::Hard => (2., 0.6),
+        };
+        base + per_letter * len as f32
+    }
+}
+
+// 本局使用的难度，新的一局开始时由 start_run 设置
+// 回放时使用录像的难度，不写入 Settings，不会改变玩家保存的选择
+#[derive(Default)]
+pub struct RunDifficulty(pub Difficulty);
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunDifficulty>()
+            .add_system_set(SystemSet::on_update(AppState::StartScreen).with_system(set_difficulty))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(apply_difficulty.label("apply_difficulty")),
+            );
+    }
+}
+
+// 处理开始屏幕上的难度选择
+fn set_difficulty(mut events: EventReader<Action>, mut settings: ResMut<Settings>) {
+    for e in events.iter() {
+        if let Action::SetDifficulty(d) = e {
+            if settings.difficulty != *d {
+                settings.difficulty = *d;
+            }
+        }
+    }
+}
+
+// 进入游戏时按难度设置速度、障碍物距离和空隙，速度从本局的起始速度开始
+// 每局都按本局的种子重新生成空隙序列，固定种子时每局关卡相同
+#[allow(clippy::too_many_arguments)]
+fn apply_difficulty(
+    run_difficulty: Res<RunDifficulty>,
+    game_rng: Res<GameRng>,
+    (mut speed, mut speed_config): (ResMut<Speed>, ResMut<SpeedConfig>),
+    mut spacing: ResMut<ObstacleSpacing>,
+    mut bag: ResMut<NextGapBag>,
+    mut step: ResMut<FlapStep>,
+    mut gravity: ResMut<Gravity>,
+    mut gap: ResMut<GapSize>,
+) {
+    let difficulty = run_difficulty.0;
+    *speed_config = SpeedConfig::for_difficulty(difficulty);
+    *speed = Speed::new(&speed_config);
+    step.0 = difficulty.flap_step();
+    gravity.0 = difficulty.gravity();
+    spacing.0 = difficulty.obstacle_spacing();
+    // 空隙序列的范围与空隙大小一致
+    *gap = GapSize::for_difficulty(difficulty);
+    *bag = NextGapBag::with_seed(
+        gap.start_range(),
+        BIRB_START_Y,
+        game_rng.stream_seed(RngStream::Gaps),
+    );
+}
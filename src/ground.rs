@@ -1,22 +1,144 @@
 use std::ops::Range;
 
 use bevy::{
+    pbr::NotShadowCaster,
     prelude::*,
-    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    render::{
+        mesh::Indices,
+        render_resource::{AddressMode, PrimitiveTopology, SamplerDescriptor},
+        texture::ImageSampler,
+    },
 };
-use rand::{thread_rng, Rng};
+use bevy_asset_loader::prelude::*;
+use rand::Rng;
 
-use crate::{AppState, Speed};
+use crate::{
+    flag,
+    luck::{GameRng, RngStream},
+    AppState, ScrollDir, Speed, TimeScale,
+};
 
+// 每块地面的默认长度和同时存在的默认块数
 pub const GROUND_LENGTH: f32 = 60.;
+const GROUND_CHUNKS: usize = 2;
 const GROUND_WIDTH: f32 = 40.;
 const GROUND_VERTICES_X: u32 = 30;
 const GROUND_VERTICES_Z: u32 = 20;
+// 地面纹理大约每隔这么长重复一次
+const GROUND_TEXTURE_TILE: f32 = 4.;
+
+// 草叶的大小和颜色
+const GRASS_BLADE_SIZE: Vec2 = Vec2::new(0.08, 0.35);
+const GRASS_COLOR: Color = Color::rgb(0.35, 0.7, 0.15);
+
+// 地面纹理，与地貌的颜色相乘
+#[derive(AssetCollection)]
+pub struct GroundAssets {
+    #[asset(path = "ground.png")]
+    pub texture: Handle<Image>,
+}
+
+// 画面细节的等级，目前决定草地上草叶的数量，配置较低的电脑可以设为 Low 关闭草叶
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+impl Quality {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    // 每块默认长度的草地上的草叶数量
+    fn grass_blades(self) -> usize {
+        match self {
+            Self::Low => 0,
+            Self::Medium => 200,
+            Self::High => 600,
+        }
+    }
+}
+
+// 地面块的数量和长度，窗口很宽或相机角度较低时可以调大，避免看到地面的尽头
+// 设置环境变量 TYPEY_BIRB_GROUND_CHUNKS 和 TYPEY_BIRB_GROUND_LENGTH 或使用 --ground-chunks、--ground-length 参数修改
+// 画面细节由 TYPEY_BIRB_QUALITY 或 --quality 参数设置，可选 low、medium、high
+pub struct GroundConfig {
+    pub chunks: usize,    // 同时存在的地面块数，至少 2 块
+    pub length: f32,      // 每块地面的长度
+    pub quality: Quality, // 画面细节
+}
+impl Default for GroundConfig {
+    fn default() -> Self {
+        Self {
+            chunks: GROUND_CHUNKS,
+            length: GROUND_LENGTH,
+            quality: Quality::default(),
+        }
+    }
+}
+impl GroundConfig {
+    fn from_env() -> Self {
+        let default = Self::default();
+        let chunks = flag("TYPEY_BIRB_GROUND_CHUNKS", "--ground-chunks").unwrap_or(default.chunks);
+        let length = flag("TYPEY_BIRB_GROUND_LENGTH", "--ground-length")
+            .filter(|l: &f32| *l > 0.)
+            .unwrap_or(default.length);
+        let quality = flag::<String>("TYPEY_BIRB_QUALITY", "--quality")
+            .and_then(|s| Quality::parse(&s))
+            .unwrap_or(default.quality);
+
+        Self {
+            chunks: chunks.max(2),
+            length,
+            quality,
+        }
+    }
+
+    // 每块草地上的草叶数量，按地面长度换算，保持密度不变
+    fn grass_blades(&self) -> usize {
+        (self.quality.grass_blades() as f32 * self.length / GROUND_LENGTH) as usize
+    }
+}
 
 // 设置游戏背景组件
 #[derive(Component)]
 pub struct Ground;
 
+// 地面的地貌，飞得越远依次变为 草地→沙地→雪地
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Grass,
+    Sand,
+    Snow,
+}
+
+impl Biome {
+    const ALL: [Biome; 3] = [Biome::Grass, Biome::Sand, Biome::Snow];
+
+    // 第几块地面属于哪种地貌，每种地貌持续 CHUNKS_PER_BIOME 块，之后循环
+    pub fn for_chunk(chunk: u32) -> Self {
+        Self::ALL[(chunk / CHUNKS_PER_BIOME) as usize % Self::ALL.len()]
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Biome::Grass => Color::rgb(0.63, 0.96, 0.26),
+            Biome::Sand => Color::rgb(0.93, 0.83, 0.52),
+            Biome::Snow => Color::rgb(0.92, 0.95, 0.98),
+        }
+    }
+}
+
+// 每种地貌持续的地面块数量
+const CHUNKS_PER_BIOME: u32 = 4;
+
 
 // 定义 GroundBundle 类型，用于在后面创建Ground组件的实体
 // 这里使用 pbr 渲染：
@@ -35,17 +157,25 @@ pub struct GroundBundle {
 impl GroundBundle {
     pub fn new(
         x: f32,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<StandardMaterial>>,
+        length: f32,
+        biome: Biome,
+        texture: &Handle<Image>,
+        rng: &mut impl Rng,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
     ) -> GroundBundle {
         Self {
             pbr: PbrBundle {
                 mesh: meshes.add(ground_mesh(
-                    Vec2::new(GROUND_LENGTH, GROUND_WIDTH),
+                    Vec2::new(length, GROUND_WIDTH),
                     UVec2::new(GROUND_VERTICES_X, GROUND_VERTICES_Z),
+                    rng,
                 )),
                 transform: Transform::from_xyz(x, 0.1, 0.),
-                material: materials.add(Color::rgb(0.63, 0.96, 0.26).into()),
+                material: materials.add(StandardMaterial {
+                    base_color_texture: Some(texture.clone()),
+                    ..biome.color().into()
+                }),
                 ..Default::default()
             },
             ground: Ground,
@@ -53,19 +183,92 @@ impl GroundBundle {
     }
 }
 
+// 草叶共用的网格和材质
+struct GrassAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+// 生成一块地面，草地上按画面细节撒上草叶
+// 草叶在地形之后使用同一个随机数，不影响地形
+#[allow(clippy::too_many_arguments)]
+fn spawn_chunk(
+    commands: &mut Commands,
+    x: f32,
+    biome: Biome,
+    rng: &mut impl Rng,
+    config: &GroundConfig,
+    (ground_assets, grass_assets): (&GroundAssets, &GrassAssets),
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let ground = GroundBundle::new(
+        x,
+        config.length,
+        biome,
+        &ground_assets.texture,
+        rng,
+        meshes,
+        materials,
+    );
+    let blades = if biome == Biome::Grass {
+        config.grass_blades()
+    } else {
+        0
+    };
+
+    // 草叶作为地面块的子实体，随地面一起移动和销毁
+    commands.spawn_bundle(ground).with_children(|parent| {
+        for _ in 0..blades {
+            let x = rng.gen_range(-config.length / 2.0..config.length / 2.0);
+            let z = rng.gen_range(-GROUND_WIDTH / 2.0..GROUND_WIDTH / 2.0);
+            let angle = rng.gen_range(-0.6..0.6);
+            let scale = rng.gen_range(0.7..1.3);
+            // 地形起伏在 ±0.1 之间，草叶根部埋进地面一点
+            let y = GRASS_BLADE_SIZE.y * scale / 2. - 0.1;
+
+            parent
+                .spawn_bundle(PbrBundle {
+                    mesh: grass_assets.mesh.clone(),
+                    material: grass_assets.material.clone(),
+                    transform: Transform::from_xyz(x, y, z)
+                        .with_rotation(Quat::from_rotation_y(angle))
+                        .with_scale(Vec3::splat(scale)),
+                    ..Default::default()
+                })
+                .insert(NotShadowCaster);
+        }
+    });
+}
+
+// 已生成的地面块数量，用于为每块地面派生随机种子
+// 每局开始时重置，相同的种子每局得到相同的地形
+#[derive(Default)]
+struct GroundChunkCount(u64);
+
+// 本局已生成的地面块数量，用于决定地貌，每局开始时重置
+#[derive(Default)]
+struct BiomeChunkCount(u32);
+
 // 定义 Gound插件
 pub struct GroundPlugin;
 
 impl Plugin for GroundPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            // 在 AppState::Playing 状态更新的时候可能的行为：
-            // 移动背景，并不断生成新的背景
-            SystemSet::on_update(AppState::Playing)
-                .with_system(ground_movement.label("ground_movement"))
-                .with_system(spawn_ground.after("ground_movement")),
-        )
-        .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup));
+        app.insert_resource(GroundConfig::from_env())
+            .init_resource::<GroundChunkCount>()
+            .init_resource::<BiomeChunkCount>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(reset_chunks.after("start_run")),
+            )
+            .add_system_set(
+                // 在 AppState::Playing 状态更新的时候可能的行为：
+                // 移动背景，并不断生成新的背景
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(ground_movement.label("ground_movement"))
+                    .with_system(spawn_ground.after("ground_movement")),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup));
     }
 }
 
@@ -73,67 +276,179 @@ impl Plugin for GroundPlugin {
 fn ground_movement(
     mut commands: Commands,
     mut query: Query<(Entity, &mut Transform), With<Ground>>,
-    time: Res<Time>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
     speed: Res<Speed>,
+    config: Res<GroundConfig>,
+    dir: Res<ScrollDir>,
 ) {
-    // 背景平移增量：按时间增量和当前速度计算
-    let delta = time.delta_seconds() * speed.current;
+    // 背景平移增量：按时间增量和当前速度计算，慢动作时跟着变慢
+    let delta = time_scale.delta_seconds(&time) * speed.current;
 
     for (entity, mut transform) in query.iter_mut() {
         // 背景平移
-        transform.translation.x -= delta;
-        // 如果平移超出范围则消除相关实体
-        if transform.translation.x < -60. {
+        transform.translation.x -= delta * dir.0;
+        // 整块地面移到原点后面一块的距离以外时消除，上面的草叶一起销毁
+        if transform.translation.x * dir.0 < -config.length {
             commands.entity(entity).despawn_recursive();
         }
     }
 }
 
 // 生成 ground 
+#[allow(clippy::too_many_arguments)]
 fn spawn_ground(
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    (ground_assets, grass_assets): (Res<GroundAssets>, Res<GrassAssets>),
     query: Query<&Transform, With<Ground>>,
+    game_rng: Res<GameRng>,
+    config: Res<GroundConfig>,
+    mut count: ResMut<GroundChunkCount>,
+    mut biome_count: ResMut<BiomeChunkCount>,
+    dir: Res<ScrollDir>,
 ) {
-    // keep two ground chunks alive at all times
+    // 始终保持配置数量的地面块
+    // 新的地面接在最前面一块的后面，镜像模式下按翻转前的坐标计算
+    let mut next_x = query
+        .iter()
+        .map(|transform| transform.translation.x * dir.0)
+        .reduce(f32::max)
+        .map_or(0., |x| x + config.length);
 
-    if query.iter().count() >= 2 {
-        return;
-    }
+    for _ in query.iter().count()..config.chunks {
+        let mut rng = game_rng.fork(RngStream::Ground(count.0));
+        count.0 += 1;
+        biome_count.0 += 1;
 
-    let max_x = query
-        .iter()
-        .max_by(|a, b| a.translation.x.partial_cmp(&b.translation.x).unwrap())
-        .unwrap()
-        .translation
-        .x;
-    // 创建实体
-    commands.spawn_bundle(GroundBundle::new(max_x + GROUND_LENGTH, meshes, materials));
+        // 创建实体
+        spawn_chunk(
+            &mut commands,
+            next_x * dir.0,
+            Biome::for_chunk(biome_count.0),
+            &mut rng,
+            &config,
+            (&ground_assets, &grass_assets),
+            &mut meshes,
+            &mut materials,
+        );
+        next_x += config.length;
+    }
 }
 
 // 初始化ground
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    ground_assets: Res<GroundAssets>,
+    game_rng: Res<GameRng>,
+    config: Res<GroundConfig>,
+    mut count: ResMut<GroundChunkCount>,
+) {
+    // 纹理在地面上重复平铺
+    if let Some(image) = images.get_mut(&ground_assets.texture) {
+        image.sampler_descriptor = ImageSampler::Descriptor(SamplerDescriptor {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            ..ImageSampler::linear_descriptor()
+        });
+    }
+
+    // 草叶是竖直的细长条，两面都能看到
+    let grass_assets = GrassAssets {
+        mesh: meshes.add(Mesh::from(shape::Quad::new(GRASS_BLADE_SIZE))),
+        material: materials.add(StandardMaterial {
+            base_color: GRASS_COLOR,
+            perceptual_roughness: 1.,
+            double_sided: true,
+            cull_mode: None,
+            ..Default::default()
+        }),
+    };
+
+    // 开始屏幕上的地面，每局开始时按本局的种子重新生成
+    spawn_first_chunk(
+        &mut commands,
+        &game_rng,
+        &config,
+        (&ground_assets, &grass_assets),
+        &mut meshes,
+        &mut materials,
+    );
+    count.0 = 1;
+    commands.insert_resource(grass_assets);
+}
+
+// 在原点生成第 0 块地面，总是草地
+// 之后的地面从第 1 块开始派生种子
+fn spawn_first_chunk(
+    commands: &mut Commands,
+    game_rng: &GameRng,
+    config: &GroundConfig,
+    assets: (&GroundAssets, &GrassAssets),
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
 ) {
-    commands.spawn_bundle(GroundBundle::new(0., meshes, materials));
+    let mut rng = game_rng.fork(RngStream::Ground(0));
+    spawn_chunk(
+        commands,
+        0.,
+        Biome::Grass,
+        &mut rng,
+        config,
+        assets,
+        meshes,
+        materials,
+    );
+}
+
+// 新的一局从草地开始：清除上一局留下的地面，按本局的种子重新生成第 0 块
+// 在 start_run 重新创建 GameRng 之后执行，回放时的地面与录制时相同
+fn reset_chunks(
+    mut commands: Commands,
+    query: Query<Entity, With<Ground>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>),
+    (ground_assets, grass_assets): (Res<GroundAssets>, Res<GrassAssets>),
+    game_rng: Res<GameRng>,
+    config: Res<GroundConfig>,
+    (mut count, mut biome_count): (ResMut<GroundChunkCount>, ResMut<BiomeChunkCount>),
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_first_chunk(
+        &mut commands,
+        &game_rng,
+        &config,
+        (&ground_assets, &grass_assets),
+        &mut meshes,
+        &mut materials,
+    );
+    count.0 = 1;
+    biome_count.0 = 0;
 }
 
 // 绘制背景网格
-pub fn ground_mesh(size: Vec2, num_vertices: UVec2) -> Mesh {
+pub fn ground_mesh(size: Vec2, num_vertices: UVec2, rng: &mut impl Rng) -> Mesh {
+    assert!(
+        num_vertices.x >= 2 && num_vertices.y >= 2,
+        "ground mesh needs at least 2x2 vertices, got {}",
+        num_vertices
+    );
     let num_quads = num_vertices - UVec2::splat(1);
     let offset = size / -2.;
+    // 纹理重复整数次，相邻两块地面的纹理可以接上
+    let tiles = (size / GROUND_TEXTURE_TILE).round().max(Vec2::ONE);
 
     let h_range: Range<f32> = -0.1..0.1;
 
-    let mut rng = thread_rng();
-
     let mut positions = vec![];
     let mut normals = vec![];
     let mut uvs = vec![];
-    let mut indices = vec![];
+    let mut colors = vec![];
 
     for x in 0..num_vertices.x {
         for z in 0..num_vertices.y {
@@ -149,10 +464,40 @@ pub fn ground_mesh(size: Vec2, num_vertices: UVec2) -> Mesh {
                 offset.y + z as f32 / num_quads.y as f32 * size.y,
             ]);
             normals.push([0., 1., 0.]);
-            uvs.push([0., 0.]);
+            uvs.push([
+                x as f32 / num_quads.x as f32 * tiles.x,
+                z as f32 / num_quads.y as f32 * tiles.y,
+            ]);
+            // 按高度微调明暗，与材质颜色相乘，起伏处形成渐变
+            let shade = 1. + h * 2.;
+            colors.push([shade, shade, shade, 1.]);
+        }
+    }
+
+    let indices = grid_indices(num_vertices);
+    // 索引错误时 GPU 会读到错误的顶点，画面出错但不会报错，调试时尽早发现
+    if cfg!(debug_assertions) {
+        if let Err(e) = validate_indices(&indices, positions.len(), num_quads) {
+            panic!("invalid ground mesh: {}", e);
         }
     }
 
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.duplicate_vertices();
+    mesh.compute_flat_normals();
+    mesh
+}
+
+// 网格的三角形索引，顶点按 x 方向逐列、每列沿 z 方向排列，每个方格两个三角形
+fn grid_indices(num_vertices: UVec2) -> Vec<u32> {
+    let num_quads = num_vertices - UVec2::splat(1);
+    let mut indices = vec![];
+
     for x in 0..num_quads.x {
         for z in 0..num_quads.y {
             let i = x * num_vertices.y + z;
@@ -168,12 +513,174 @@ pub fn ground_mesh(size: Vec2, num_vertices: UVec2) -> Mesh {
         }
     }
 
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    mesh.set_indices(Some(Indices::U32(indices)));
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    mesh.duplicate_vertices();
-    mesh.compute_flat_normals();
-    mesh
+    indices
+}
+
+// 检查网格索引：每个索引都在顶点范围内，三角形数量为 num_quads.x * num_quads.y * 2
+fn validate_indices(indices: &[u32], vertex_count: usize, num_quads: UVec2) -> Result<(), String> {
+    if !indices.len().is_multiple_of(3) {
+        return Err(format!(
+            "{} indices is not a whole number of triangles",
+            indices.len()
+        ));
+    }
+
+    let triangles = indices.len() / 3;
+    let expected = (num_quads.x * num_quads.y * 2) as usize;
+    if triangles != expected {
+        return Err(format!("{} triangles, expected {}", triangles, expected));
+    }
+
+    match indices.iter().find(|i| **i as usize >= vertex_count) {
+        Some(i) => Err(format!(
+            "index {} out of range for {} vertices",
+            i, vertex_count
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(game_rng: &GameRng, chunk: u64) -> Vec<[f32; 3]> {
+        let mesh = ground_mesh(
+            Vec2::new(GROUND_LENGTH, GROUND_WIDTH),
+            UVec2::new(GROUND_VERTICES_X, GROUND_VERTICES_Z),
+            &mut game_rng.fork(RngStream::Ground(chunk)),
+        );
+        mesh_positions(&mesh)
+    }
+
+    fn mesh_positions(mesh: &Mesh) -> Vec<[f32; 3]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) => {
+                positions.clone()
+            }
+            _ => panic!("expected Float32x3 positions"),
+        }
+    }
+
+    #[test]
+    fn same_seed_and_chunk_give_same_terrain() {
+        let game_rng = GameRng::new(42);
+        assert_eq!(positions(&game_rng, 3), positions(&game_rng, 3));
+    }
+
+    #[test]
+    fn different_chunks_give_different_terrain() {
+        let game_rng = GameRng::new(42);
+        assert_ne!(positions(&game_rng, 3), positions(&game_rng, 4));
+    }
+
+    #[test]
+    fn new_run_regrows_the_first_chunk_from_its_seed() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .insert_resource(GroundAssets {
+                texture: Handle::default(),
+            })
+            .insert_resource(GrassAssets {
+                mesh: Handle::default(),
+                material: Handle::default(),
+            })
+            .insert_resource(GameRng::new(42))
+            .insert_resource(GroundConfig::default())
+            .insert_resource(GroundChunkCount(5))
+            .insert_resource(BiomeChunkCount(3))
+            .add_system(reset_chunks);
+        // 上一局留下的地面
+        app.world.spawn().insert(Ground);
+        app.world.spawn().insert(Ground);
+        app.update();
+
+        let mut query = app.world.query_filtered::<&Handle<Mesh>, With<Ground>>();
+        let handles = query.iter(&app.world).cloned().collect::<Vec<_>>();
+        assert_eq!(handles.len(), 1);
+        let mesh = app
+            .world
+            .resource::<Assets<Mesh>>()
+            .get(&handles[0])
+            .unwrap();
+        assert_eq!(mesh_positions(mesh), positions(&GameRng::new(42), 0));
+        assert_eq!(app.world.resource::<GroundChunkCount>().0, 1);
+        assert_eq!(app.world.resource::<BiomeChunkCount>().0, 0);
+    }
+
+    const GRID_SIZES: [(u32, u32); 4] = [
+        (2, 2),
+        (3, 5),
+        (7, 2),
+        (GROUND_VERTICES_X, GROUND_VERTICES_Z),
+    ];
+
+    #[test]
+    fn grid_indices_are_valid_for_several_sizes() {
+        for (x, z) in GRID_SIZES {
+            let num_vertices = UVec2::new(x, z);
+            let indices = grid_indices(num_vertices);
+            let vertex_count = (x * z) as usize;
+
+            assert_eq!(
+                validate_indices(&indices, vertex_count, num_vertices - UVec2::ONE),
+                Ok(()),
+                "{}x{}",
+                x,
+                z
+            );
+            // 每个顶点都被用到
+            for v in 0..vertex_count as u32 {
+                assert!(indices.contains(&v), "{}x{}: vertex {} unused", x, z, v);
+            }
+        }
+    }
+
+    #[test]
+    fn ground_mesh_has_two_triangles_per_quad() {
+        for (x, z) in GRID_SIZES {
+            let mesh = ground_mesh(
+                Vec2::new(GROUND_LENGTH, GROUND_WIDTH),
+                UVec2::new(x, z),
+                &mut GameRng::new(42).fork(RngStream::Ground(0)),
+            );
+
+            // duplicate_vertices 之后每个三角形有自己的三个顶点
+            let triangles = ((x - 1) * (z - 1) * 2) as usize;
+            assert_eq!(mesh.count_vertices(), triangles * 3, "{}x{}", x, z);
+        }
+    }
+
+    #[test]
+    fn validation_catches_bad_indices() {
+        let num_vertices = UVec2::new(3, 3);
+        let num_quads = num_vertices - UVec2::ONE;
+        let mut indices = grid_indices(num_vertices);
+
+        indices[4] = 9;
+        assert!(validate_indices(&indices, 9, num_quads).is_err());
+
+        indices.truncate(6);
+        assert!(validate_indices(&indices, 9, num_quads).is_err());
+    }
+
+    #[test]
+    fn grass_density_follows_quality_and_length() {
+        let config = |quality, length| GroundConfig {
+            chunks: GROUND_CHUNKS,
+            length,
+            quality,
+        };
+
+        assert_eq!(Quality::parse(" LOW "), Some(Quality::Low));
+        assert_eq!(Quality::parse("ultra"), None);
+        assert_eq!(config(Quality::Low, GROUND_LENGTH).grass_blades(), 0);
+        assert_eq!(
+            config(Quality::High, GROUND_LENGTH * 2.).grass_blades(),
+            Quality::High.grass_blades() * 2
+        );
+    }
 }
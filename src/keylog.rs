@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::{
+    save::{save_text, timestamp},
+    typing::{KeystrokeLogged, LogKeystrokes},
+    AppState,
+};
+
+// 按键日志插件：开启 LogKeystrokes 时收集本局的每个按键，离开游戏时写入 CSV
+// 每局一个文件，保存在存档目录中，文件名带有结束时的时间戳
+pub struct KeylogPlugin;
+
+// 本局收集到的按键
+#[derive(Default)]
+struct Keylog(Vec<KeystrokeLogged>);
+
+impl Plugin for KeylogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Keylog>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing).with_system(collect_keys.after("keyboard")),
+            )
+            // 结束屏幕和中途退出都会离开游戏状态，暂停不会
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(flush_keys));
+    }
+}
+
+fn collect_keys(
+    mut events: EventReader<KeystrokeLogged>,
+    mut keylog: ResMut<Keylog>,
+    log_keystrokes: Res<LogKeystrokes>,
+) {
+    if !log_keystrokes.0 {
+        return;
+    }
+    keylog.0.extend(events.iter().cloned());
+}
+
+fn flush_keys(mut keylog: ResMut<Keylog>) {
+    if keylog.0.is_empty() {
+        return;
+    }
+
+    let file = format!("keystrokes-{}.csv", timestamp());
+    save_text(&file, &to_csv(&keylog.0));
+    info!("Logged {} keystrokes to {}", keylog.0.len(), file);
+    keylog.0.clear();
+}
+
+// 每行一个按键：游戏时间、字符、是否正确
+// 字符统一加上引号，逗号和引号也能正确读取
+fn to_csv(keys: &[KeystrokeLogged]) -> String {
+    let mut csv = String::from("elapsed,char,correct\n");
+    for key in keys {
+        let c = if key.char == '"' {
+            "\"\"".to_owned()
+        } else {
+            key.char.to_string()
+        };
+        csv.push_str(&format!("{:.3},\"{}\",{}\n", key.elapsed, c, key.correct));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_every_character() {
+        let key = |char, correct, elapsed| KeystrokeLogged {
+            char,
+            correct,
+            elapsed,
+        };
+        let csv = to_csv(&[
+            key('a', true, 0.5),
+            key(',', false, 1.25),
+            key('"', false, 2.),
+        ]);
+
+        assert_eq!(
+            csv,
+            "elapsed,char,correct\n0.500,\"a\",true\n1.250,\",\",false\n2.000,\"\"\"\",false\n"
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use bevy::{
+    asset::{FileAssetIo, LoadState},
+    prelude::*,
+};
+use bevy_asset_loader::prelude::*;
+
+use crate::{
+    ground::GroundAssets, locale::Locale, AppState, AudioAssets, FontAssets, GltfAssets,
+    TextureAssets,
+};
+
+// 加载检查插件：资源加载失败或超时没有完成时，显示缺少的资源路径，而不是一直黑屏
+// 最常见的原因是移动了 assets 目录
+pub struct LoadingPlugin;
+
+// 超过这个时间（秒）还没有加载完，就当作加载失败，列出还在加载的资源
+const LOADING_TIMEOUT_SECONDS: f32 = 15.;
+
+const WINDOW_TITLE: &str = "Typey Birb";
+
+// 所有资源集合的句柄，与 bevy_asset_loader 加载的是同一批资源
+struct LoadingAssets {
+    handles: Vec<HandleUntyped>,
+    fonts: Vec<HandleUntyped>, // 字体本身也可能加载失败，加载成功时才能显示错误文字
+    timer: Timer,
+    error: Option<String>, // 加载失败时的错误信息
+    shown: bool,           // 已经在屏幕上显示了错误
+}
+
+// 错误屏幕上的实体，加载最终完成时清除
+#[derive(Component)]
+struct LoadingError;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Loading).with_system(collect_handles.exclusive_system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Loading)
+                .with_system(check_loading.label("check_loading"))
+                .with_system(show_loading_error.after("check_loading")),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(clear_loading_error));
+    }
+}
+
+// 资源服务器按路径去重，再加载一次得到的是同一批句柄
+fn collect_handles(world: &mut World) {
+    let fonts = FontAssets::load(world);
+    let mut handles = GltfAssets::load(world);
+    handles.extend(AudioAssets::load(world));
+    handles.extend(TextureAssets::load(world));
+    handles.extend(GroundAssets::load(world));
+    handles.extend(fonts.iter().cloned());
+
+    world.insert_resource(LoadingAssets {
+        handles,
+        fonts,
+        timer: Timer::from_seconds(LOADING_TIMEOUT_SECONDS, false),
+        error: None,
+        shown: false,
+    });
+}
+
+// 有资源加载失败时立即报错，超时后把还没加载完的资源也列出来
+fn check_loading(
+    mut loading: ResMut<LoadingAssets>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    locale: Res<Locale>,
+    mut windows: ResMut<Windows>,
+) {
+    if loading.error.is_some() {
+        return;
+    }
+    loading.timer.tick(time.delta());
+
+    let states: Vec<_> = loading
+        .handles
+        .iter()
+        .map(|handle| {
+            let path = asset_server
+                .get_handle_path(handle)
+                .map_or_else(|| "?".to_string(), |path| path.path().display().to_string());
+            (path, asset_server.get_load_state(handle))
+        })
+        .collect();
+    let failed = states.iter().any(|(_, state)| *state == LoadState::Failed);
+    if !failed && !loading.timer.finished() {
+        return;
+    }
+    let missing = missing_assets(&locale, &states);
+    if missing.is_empty() {
+        return;
+    }
+
+    let assets_dir = FileAssetIo::get_base_path().join("assets");
+    let message = error_message(&locale, &assets_dir.display().to_string(), &missing);
+    error!("{}", message);
+    // 字体缺失时屏幕上无法显示文字，标题栏总能看到
+    if let Some(window) = windows.get_primary_mut() {
+        let reason = locale.get("loading_failed").trim_end_matches(':');
+        window.set_title(format!("{} - {}", WINDOW_TITLE, reason));
+    }
+    loading.error = Some(message);
+}
+
+// 没有加载成功的资源及其状态，同一个文件只列一次
+fn missing_assets(locale: &Locale, states: &[(String, LoadState)]) -> Vec<String> {
+    let mut missing = Vec::new();
+    for (path, state) in states {
+        let status = match state {
+            LoadState::Loaded => continue,
+            LoadState::Failed => locale.get("loading_asset_failed"),
+            _ => locale.get("loading_asset_slow"),
+        };
+        let line = format!("{} ({})", path, status);
+        if !missing.contains(&line) {
+            missing.push(line);
+        }
+    }
+    missing
+}
+
+fn error_message(locale: &Locale, assets_dir: &str, missing: &[String]) -> String {
+    format!(
+        "{}\n\n{}\n\n{}\n{}",
+        locale.get("loading_failed"),
+        missing.join("\n"),
+        locale.get("loading_assets_dir"),
+        assets_dir
+    )
+}
+
+// 找到一个加载成功的字体后显示错误信息，加载阶段还没有摄像机，需要自己生成一个
+fn show_loading_error(
+    mut commands: Commands,
+    mut loading: ResMut<LoadingAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if loading.shown {
+        return;
+    }
+    let message = match &loading.error {
+        Some(message) => message.clone(),
+        None => return,
+    };
+    let font = match loading
+        .fonts
+        .iter()
+        .find(|font| asset_server.get_load_state(*font) == LoadState::Loaded)
+    {
+        Some(font) => font.clone().typed::<Font>(),
+        None => return,
+    };
+
+    commands
+        .spawn_bundle(Camera2dBundle::default())
+        .insert(LoadingError);
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::from_section(
+                message,
+                TextStyle {
+                    font,
+                    font_size: 32.,
+                    color: Color::rgb(0.9, 0.2, 0.2),
+                },
+            ),
+            ..Default::default()
+        })
+        .insert(LoadingError);
+    loading.shown = true;
+}
+
+// 超时以后资源最终还是加载完了，清除错误屏幕，恢复标题
+fn clear_loading_error(
+    mut commands: Commands,
+    query: Query<Entity, With<LoadingError>>,
+    loading: Res<LoadingAssets>,
+    mut windows: ResMut<Windows>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if loading.error.is_some() {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_title(WINDOW_TITLE.to_string());
+        }
+    }
+    commands.remove_resource::<LoadingAssets>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unloaded_assets_are_listed_once() {
+        let locale = Locale::default();
+        let states = [
+            ("bevybird.glb".to_string(), LoadState::Failed),
+            ("bevybird.glb".to_string(), LoadState::Failed),
+            ("flap.ogg".to_string(), LoadState::Loaded),
+            ("pipe.png".to_string(), LoadState::Loading),
+        ];
+
+        assert_eq!(
+            missing_assets(&locale, &states),
+            ["bevybird.glb (failed to load)", "pipe.png (still loading)"]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::Deserialize;
+
+use crate::{flag, AppState, FontAssets};
+
+// 本地化插件：界面文字按键从当前语言的字符串表中查找
+pub struct LocalePlugin;
+
+// 内置的英文字符串，其它语言缺少某个键时也使用这里的值
+const ENGLISH: &[(&str, &str)] = &[
+    ("paused", "PAUSED - press ESC to resume, CTRL+Q to quit"),
+    (
+        "start_prompt",
+        "So you want to join the flock, eh?\nYou'll have to beat me first!\nType the word below when you're ready.",
+    ),
+    ("best", "BEST"),
+    ("new_best", "NEW BEST!"),
+    ("daily_challenge", "Daily Challenge"),
+    ("top_scores", "TOP SCORES"),
+    ("top_ten_initials", "TOP 10! YOUR INITIALS:"),
+    ("quit_prompt", "Quit? type 'yes'"),
+    ("on_hit_die", "ON HIT: DIE"),
+    ("on_hit_bounce", "ON HIT: BOUNCE"),
+    ("on_hit_practice", "ON HIT: NO DEATH"),
+    ("birb", "BIRB"),
+    ("difficulty", "DIFFICULTY"),
+    ("words", "WORDS"),
+    ("score", "SCORE"),
+    ("points", "POINTS!"),
+    ("wpm", "WPM"),
+    ("accuracy", "ACC"),
+    ("perfect_words", "PERFECT WORDS"),
+    ("distance", "DISTANCE"),
+    ("options", "OPTIONS"),
+    (
+        "options_hint",
+        "UP/DOWN to choose, LEFT/RIGHT or 0-9 to adjust, ENTER to go back",
+    ),
+    ("music", "MUSIC"),
+    ("sfx", "SFX"),
+    ("text_size", "TEXT SIZE"),
+    ("gap_preview", "GAP PREVIEW"),
+    ("on", "ON"),
+    ("off", "OFF"),
+    ("death_beat_rival", "You beat the flock!"),
+    ("death_passed_rival", "You passed the flock, for a while!"),
+    (
+        "death_mashed",
+        "You beat the flock...\nby mashing every key in sight?\nWe demand a rematch!",
+    ),
+    (
+        "death_fast_sloppy",
+        "You were fast but sloppy!\nSlow down a little and the pipes\nwon't sneak up on you.",
+    ),
+    (
+        "death_quick_clean",
+        "Quick and clean!\nAre you sure you're not\na birb in disguise?",
+    ),
+    (
+        "death_slow_steady",
+        "Slow but steady!\nNot a single feather out of place.",
+    ),
+    (
+        "death_wow",
+        "I... wha... wow!\nWhat am I even doing with my life?\nThe flock is yours, if you'll have us!",
+    ),
+    (
+        "death_close_one",
+        "That was a close one!\nWith moves like that, you'll\nfit in well here!",
+    ),
+    (
+        "death_apprentice",
+        "Not bad, kid!\nThere may be room for you in the flock\nas an unpaid apprentice.",
+    ),
+    (
+        "death_ouch",
+        "Oh wow, ouch!\nToo bad you're stuck at Z = 0.0,\nthe path is a bit clearer a few units over.",
+    ),
+    ("loading_failed", "Some game files could not be loaded:"),
+    ("loading_asset_failed", "failed to load"),
+    ("loading_asset_slow", "still loading"),
+    ("loading_assets_dir", "Looking for them in:"),
+];
+
+// 语言文件 assets/locale/<语言>.ron 的内容
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LocaleFile {
+    font: Option<String>, // 这个语言使用的字体，路径相对 assets 目录，为 None 时使用默认字体
+    strings: HashMap<String, String>,
+}
+
+// 当前语言的界面文字
+pub struct Locale {
+    strings: HashMap<String, String>,
+    font: Option<String>,
+}
+impl Default for Locale {
+    fn default() -> Self {
+        Self::from_file(LocaleFile::default())
+    }
+}
+impl Locale {
+    // 设置环境变量 TYPEY_BIRB_LANG 或使用 --lang 参数选择语言，例如 TYPEY_BIRB_LANG=es 读取 assets/locale/es.ron
+    // 文件不存在或无法解析时使用英文
+    pub fn from_env() -> Self {
+        let lang = match flag::<String>("TYPEY_BIRB_LANG", "--lang") {
+            Some(lang) if !lang.is_empty() => lang.to_lowercase(),
+            _ => return Self::default(),
+        };
+        if lang == "en" {
+            return Self::default();
+        }
+
+        let path = FileAssetIo::get_base_path()
+            .join("assets")
+            .join("locale")
+            .join(format!("{}.ron", lang));
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match ron::from_str(&contents) {
+            Ok(file) => Self::from_file(file),
+            Err(e) => {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    // 语言文件中的字符串覆盖英文
+    fn from_file(file: LocaleFile) -> Self {
+        let mut strings: HashMap<String, String> = ENGLISH
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        for (key, value) in file.strings {
+            if !strings.contains_key(&key) {
+                warn!("Unknown locale key {}", key);
+            }
+            strings.insert(key, value);
+        }
+
+        Self {
+            strings,
+            font: file.font,
+        }
+    }
+
+    // 查找字符串，没有这个键时直接显示键名，便于发现遗漏
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map_or(key, String::as_str)
+    }
+}
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Locale::from_env()).add_system_set(
+            SystemSet::on_exit(AppState::Loading)
+                .with_system(apply_locale_font.label("locale_font")),
+        );
+    }
+}
+
+// 语言指定了字体时替换默认字体，需要在生成界面文字之前执行
+fn apply_locale_font(
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+    mut font_assets: ResMut<FontAssets>,
+) {
+    if let Some(font) = &locale.font {
+        font_assets.main = asset_server.load(font.as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_strings_fall_back_to_english() {
+        let locale = Locale::from_file(LocaleFile {
+            font: None,
+            strings: [("best".to_string(), "MEJOR".to_string())].into(),
+        });
+        assert_eq!(locale.get("best"), "MEJOR");
+        assert_eq!(locale.get("score"), "SCORE");
+        assert_eq!(locale.get("no_such_key"), "no_such_key");
+    }
+
+    #[test]
+    fn bundled_locales_only_use_known_keys() {
+        for contents in [include_str!("../assets/locale/es.ron")] {
+            let file: LocaleFile = ron::from_str(contents).unwrap();
+            for key in file.strings.keys() {
+                assert!(ENGLISH.iter().any(|(k, _)| k == key), "unknown key {}", key);
+            }
+            assert_eq!(file.strings.len(), ENGLISH.len());
+        }
+    }
+}
@@ -1,9 +1,14 @@
 use rand::prelude::*;
-use std::ops::Range;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+};
 
-// 上下障碍物之间空隙的大小规格
-#[derive(Debug)]
-enum NextGapKind {
+// 上下障碍物之间空隙的大小规格：与上一个空隙相比移动的距离，占空隙坐标范围的比例
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NextGapKind {
     VerySmall,
     Small,
     Medium,
@@ -11,7 +16,7 @@ enum NextGapKind {
     VeryLarge,
 }
 impl NextGapKind {
-    fn to_range(&self) -> Range<f32> {
+    pub fn to_range(self) -> Range<f32> {
         match self {
             NextGapKind::VerySmall => 0.1..0.2,
             NextGapKind::Small => 0.2..0.3,
@@ -21,16 +26,149 @@ impl NextGapKind {
         }
     }
 }
+// 随机种子。为 None 时每局使用新的随机种子
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Seed(pub Option<u64>);
+impl Seed {
+    // 从环境变量 TYPEY_BIRB_SEED 或 --seed 参数读取种子
+    pub fn from_env() -> Self {
+        Self(crate::flag("TYPEY_BIRB_SEED", "--seed"))
+    }
+
+    // 每日挑战：由当前 UTC 日期得到种子，同一天所有人的关卡相同
+    pub fn daily() -> Self {
+        Self(Some(daily_seed()))
+    }
+}
+
+// 随机数的子流，每个功能使用自己的子流
+// 各功能抽取的随机数互不影响，一个功能多抽或少抽不会改变其它功能的结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Gaps,        // 障碍物的空隙、形状和移动
+    Words,       // 单词顺序
+    Ground(u64), // 第 n 块地面
+    Particles,   // 粒子效果
+    CameraShake, // 镜头抖动
+    Pitch,       // 音效的音调
+}
+impl RngStream {
+    fn id(self) -> u64 {
+        match self {
+            RngStream::Gaps => 0,
+            RngStream::Words => 1,
+            RngStream::Particles => 2,
+            RngStream::CameraShake => 3,
+            RngStream::Pitch => 4,
+            RngStream::Ground(chunk) => chunk.wrapping_add(16),
+        }
+    }
+}
+
+// 全局的随机数发生器，所有随机数都由同一个种子按子流派生
+// 每局开始时按本局的种子重新创建（见 recording.rs 中的 start_run），固定种子时整局可以复现
+// 同一个子流只应由一个系统抽取，或者抽取的系统之间有明确的执行顺序，否则抽取的先后不确定
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha12Rng>,
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    // 没有指定种子时随机选择一个
+    pub fn from_seed(seed: Seed) -> Self {
+        Self::new(seed.0.unwrap_or_else(|| thread_rng().gen()))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 取得子流，第一次使用时创建，之后从上次抽取的位置继续
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha12Rng {
+        let seed = self.seed;
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| derive_rng(seed, stream))
+    }
+
+    // 新建一个子流的随机数发生器，交给需要自己持有随机数发生器的地方，如空隙序列和单词列表
+    // 每次调用都从子流的开头开始
+    pub fn fork(&self, stream: RngStream) -> ChaCha12Rng {
+        derive_rng(self.seed, stream)
+    }
+
+    // 子流的种子，用于只接受种子的地方，如 NextGapBag::with_seed
+    pub fn stream_seed(&self, stream: RngStream) -> u64 {
+        derive_seed(self.seed, stream)
+    }
+}
+
+fn derive_seed(seed: u64, stream: RngStream) -> u64 {
+    seed ^ stream
+        .id()
+        .wrapping_add(1)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+fn derive_rng(seed: u64, stream: RngStream) -> ChaCha12Rng {
+    ChaCha12Rng::seed_from_u64(derive_seed(seed, stream))
+}
+
+// 每日挑战是否开启
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DailyChallenge(pub bool);
+impl DailyChallenge {
+    // 设置环境变量 TYPEY_BIRB_DAILY 或使用 --daily 参数开启
+    pub fn from_env() -> Self {
+        Self(crate::switch("TYPEY_BIRB_DAILY", "--daily"))
+    }
+}
+
+// 以自 1970-01-01 起的 UTC 天数计算种子
+pub fn daily_seed() -> u64 {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0);
+
+    seed_for_day(days)
+}
+
+fn seed_for_day(days: u64) -> u64 {
+    // 打散相邻日期，避免连续几天的种子过于接近
+    let mut x = days.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+// 与 GameRng 一样使用 ChaCha12Rng，算法固定，并且可以序列化，用于 GameSnapshot
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NextGapBag {
-    rng: StdRng, // 使用 rand 的 RNG(随机数发生器)
+    rng: ChaCha12Rng,       // 使用 rand 的 RNG(随机数发生器)，只用于空隙
+    shape_rng: ChaCha12Rng, // 障碍物形状等其它属性使用的随机数发生器
     index: usize,
     range: Range<f32>,
     previous_value: f32,
     contents: Vec<NextGapKind>,
+    upcoming: VecDeque<(f32, NextGapKind)>, // preview 提前生成、还没有被 next 取走的空隙
 }
 impl NextGapBag {
-    pub fn new(range: Range<f32>, initial_value: f32) -> Self {
-        let mut rng = StdRng::from_entropy(); // 创建新的随机种子
+    // 使用固定的种子，相同种子产生相同的障碍物序列
+    pub fn with_seed(range: Range<f32>, initial_value: f32, seed: u64) -> Self {
+        Self::with_rng(range, initial_value, ChaCha12Rng::seed_from_u64(seed))
+    }
+
+    // 使用 GameRng 的 Gaps 子流，相同种子产生相同的障碍物序列
+    // 空隙和其它属性分开抽取，预览空隙不会影响之后障碍物的形状
+    pub fn with_rng(range: Range<f32>, initial_value: f32, mut rng: ChaCha12Rng) -> Self {
+        let shape_rng = ChaCha12Rng::seed_from_u64(rng.gen());
 
         let mut contents = vec![
             NextGapKind::VerySmall,
@@ -57,24 +195,87 @@ impl NextGapBag {
 
         Self {
             rng,
+            shape_rng,
             range,
             previous_value: initial_value,
             index: 0,
             contents,
+            upcoming: VecDeque::new(),
         }
     }
 }
 
+// next_shape 偶尔把空隙变窄、变宽的比例
+pub const NARROW_GAP_SCALE: f32 = 0.85;
+pub const WIDE_GAP_SCALE: f32 = 1.15;
+
+// 障碍物的形状：圆柱体半径和空隙大小
+#[derive(Clone, Copy, Debug)]
+pub struct PipeShape {
+    pub radius: f32,
+    pub gap_size: f32,
+}
+
+impl NextGapBag {
+    // 随机生成障碍物的形状
+    // 半径在 radius 附近小幅变化；偶尔让空隙变窄或变宽，但不会小于 min_gap_size
+    pub fn next_shape(&mut self, radius: f32, gap_size: f32, min_gap_size: f32) -> PipeShape {
+        let radius = radius * self.shape_rng.gen_range(0.8..1.2);
+        let gap_size = match self.shape_rng.gen_range(0..10) {
+            0 | 1 => gap_size * NARROW_GAP_SCALE,
+            2 | 3 => gap_size * WIDE_GAP_SCALE,
+            _ => gap_size,
+        };
+
+        PipeShape {
+            radius,
+            gap_size: gap_size.max(min_gap_size),
+        }
+    }
+
+    // 查看接下来的 n 个空隙而不取走，之后的 next 按顺序返回这些值
+    pub fn preview(&mut self, n: usize) -> impl ExactSizeIterator<Item = f32> + '_ {
+        while self.upcoming.len() < n {
+            let val = self.generate();
+            self.upcoming.push_back(val);
+        }
+        self.upcoming.iter().map(|&(val, _)| val).take(n)
+    }
+
+    // 与 next_shape 共用同一个随机数发生器，固定种子时其它随机的障碍物属性也能复现
+    pub fn rng(&mut self) -> &mut ChaCha12Rng {
+        &mut self.shape_rng
+    }
+}
+
 // 实现一个随机获取gap的迭代器
 impl Iterator for NextGapBag {
     type Item = f32;
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_kind().map(|(val, _)| val)
+    }
+}
+
+impl NextGapBag {
+    // 与 next 相同，同时返回生成这个空隙时使用的规格
+    pub fn next_with_kind(&mut self) -> Option<(f32, NextGapKind)> {
+        // 先取走 preview 提前生成的空隙
+        Some(match self.upcoming.pop_front() {
+            Some(gap) => gap,
+            None => self.generate(),
+        })
+    }
+}
+
+impl NextGapBag {
+    // 生成下一个空隙
+    fn generate(&mut self) -> (f32, NextGapKind) {
         if self.index >= self.contents.len() {
             self.index = 0;
             self.contents.shuffle(&mut self.rng);
         }
 
-        let kind = self.contents.get(self.index).unwrap();
+        let kind = *self.contents.get(self.index).unwrap();
         let kind_range = kind.to_range();
 
         let magnitude = self.range.end - self.range.start;
@@ -112,6 +313,109 @@ impl Iterator for NextGapBag {
 
         self.index += 1;
 
-        Some(val)
+        (val, kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bag() -> NextGapBag {
+        NextGapBag::with_seed(0.0..10.0, 5.0, 7)
+    }
+
+    #[test]
+    fn next_returns_previewed_gaps_in_order() {
+        let mut bag = bag();
+        let preview = bag.preview(12).collect::<Vec<_>>();
+        assert_eq!(preview.len(), 12);
+
+        // 再次预览不会改变结果
+        assert_eq!(bag.preview(5).collect::<Vec<_>>(), preview[..5]);
+
+        let taken = bag.by_ref().take(12).collect::<Vec<_>>();
+        assert_eq!(taken, preview);
+    }
+
+    #[test]
+    fn preview_does_not_change_the_gap_sequence() {
+        let expected = bag().take(20).collect::<Vec<_>>();
+
+        let mut previewed = bag();
+        let mut gaps = previewed.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(previewed.preview(10).len(), 10);
+        gaps.extend(previewed.take(17));
+
+        assert_eq!(gaps, expected);
+    }
+
+    #[test]
+    fn preview_does_not_change_obstacle_shapes() {
+        let shapes = |preview: bool| {
+            let mut bag = bag();
+            (0..10)
+                .map(|_| {
+                    if preview {
+                        bag.preview(5).for_each(drop);
+                    }
+                    bag.next();
+                    bag.next_shape(1., 2., 0.5).radius
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(shapes(true), shapes(false));
+    }
+
+    #[test]
+    fn same_seed_gives_same_gaps() {
+        let gaps = |seed| {
+            NextGapBag::with_seed(0.0..10.0, 5.0, seed)
+                .take(20)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(gaps(7), gaps(7));
+        assert_ne!(gaps(7), gaps(8));
+    }
+
+    #[test]
+    fn with_seed_matches_the_game_rng_stream() {
+        let game_rng = GameRng::new(42);
+        let seeded = NextGapBag::with_seed(0.0..10.0, 5.0, game_rng.stream_seed(RngStream::Gaps));
+        let forked = NextGapBag::with_rng(0.0..10.0, 5.0, game_rng.fork(RngStream::Gaps));
+
+        assert_eq!(
+            seeded.take(20).collect::<Vec<_>>(),
+            forked.take(20).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reported_kind_matches_the_gap_delta() {
+        let mut bag = bag();
+        let range = 0.0..10.0;
+        let magnitude = range.end - range.start;
+        let mut previous = 5.0;
+
+        for _ in 0..200 {
+            let (val, kind) = bag.next_with_kind().unwrap();
+            let delta = (val - previous).abs() / magnitude;
+            previous = val;
+
+            // 贴着范围边界时移动距离会被截断
+            if val <= range.start || val >= range.end {
+                continue;
+            }
+            let kind_range = kind.to_range();
+            assert!(
+                delta >= kind_range.start - 1e-4 && delta <= kind_range.end + 1e-4,
+                "{:?} jump of {} outside {:?}",
+                kind,
+                delta,
+                kind_range
+            );
+        }
     }
 }
@@ -1,31 +1,75 @@
 #![allow(clippy::forget_non_drop)] // https://github.com/bevyengine/bevy/issues/4601
 
 use bevy::{
+    app::AppExit,
     audio::AudioSink,
     log::{Level, LogSettings},
     math::Vec3A,
     prelude::*,
     render::primitives::Aabb,
+    window::WindowFocused,
 };
 
 // 使用第三方库(bevy 插件) bevy_asset_loader 来管理 Assets
 use bevy_asset_loader::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, str::FromStr, time::Duration};
 
 // 使用 bevy_inspector_egui 可以进行可视化调试
 #[cfg(feature = "inspector")]
 use bevy_inspector_egui::WorldInspectorPlugin;
-use luck::NextGapBag;
-// 使用 bevy 提供的 `bevy::render::primitives::Aabb` 功能进行碰撞检测
-use util::collide_aabb;
+#[cfg(feature = "inspector")]
+use bevy_prototype_debug_lines::{DebugLines, DebugLinesPlugin};
 
+use audio::{
+    note_speed, play_sfx, play_sfx_quiet, play_sfx_scaled, play_sfx_varied, MusicController,
+    PlaySfx,
+};
+use background::Background;
+use camera::{camera_transform, CameraShake};
+use difficulty::{Difficulty, RunDifficulty};
+use luck::{DailyChallenge, GameRng, NextGapBag, RngStream, Seed, WIDE_GAP_SCALE};
+use particles::{BurstKind, Particle, ParticleBurst};
+use save::Settings;
+use typing::TextCapture;
+// 使用 bevy 提供的 `bevy::render::primitives::Aabb` 功能进行碰撞检测
+use util::{aabb_overlap, collide_aabb, swept_collide_aabb};
+
+// 音效和音乐
+mod audio;
+// 远景：山丘和云朵
+mod background;
+// 摄像机
+mod camera;
 // 圆柱体障碍
 mod cylinder;
+// 难度选择
+mod difficulty;
 // 游戏背景
 mod ground;
 // 随机产生圆柱体大小、间隔
 mod luck;
 // 处理键盘输入的打字模块
 mod typing;
+// 录像和回放
+mod recording;
+// 界面文字的本地化
+mod locale;
+// 按键日志：把每个按键的时间写入 CSV，供打字研究使用
+mod keylog;
+// 资源加载失败时的错误屏幕
+mod loading;
+// 存档：最高分和设置
+mod save;
+// 游戏状态快照，用于复现问题和确定性测试
+mod snapshot;
+// 撞击和得分时的粒子效果
+mod particles;
+// 圆锥体、胶囊体障碍
+mod shapes;
+// 昼夜循环
+mod sky;
 // 游戏 UI 界面模块
 mod ui;
 // 工具模块
@@ -48,6 +92,94 @@ struct GltfAssets {
     birb: Handle<Scene>, // 玩家操控的鸟
 }
 
+// 玩家的鸟使用的模型，保存在 Settings 中，在开始屏幕上输入 skin 切换
+// 竞争鸟使用另一种模型，两者始终可以分辨
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BirbSkin {
+    #[default]
+    Blue,
+    Gold,
+}
+impl BirbSkin {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blue => "blue",
+            Self::Gold => "gold",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::Blue => Self::Gold,
+            Self::Gold => Self::Blue,
+        }
+    }
+
+    // 竞争鸟的模型
+    pub fn rival(self) -> Self {
+        self.next()
+    }
+
+    fn scene(self, gltf_assets: &GltfAssets) -> Handle<Scene> {
+        match self {
+            Self::Blue => gltf_assets.birb.clone(),
+            Self::Gold => gltf_assets.birb_gold.clone(),
+        }
+    }
+}
+
+// 使用竞争鸟模型的实体，包括开始屏幕和结束屏幕上装饰用的鸟
+#[derive(Component)]
+struct RivalSkin;
+
+// 读取环境变量 env 或命令行参数 arg 的值，环境变量优先
+// 参数写成 --arg=value；只写 --arg 时值为空字符串，用于开关
+// 值无法解析时当作没有设置
+fn flag<T: FromStr>(env: &str, arg: &str) -> Option<T> {
+    parse_flag(std::env::var(env).ok(), std::env::args(), arg)
+}
+
+fn parse_flag<T: FromStr>(
+    value: Option<String>,
+    mut args: impl Iterator<Item = String>,
+    arg: &str,
+) -> Option<T> {
+    let value = value.or_else(|| {
+        args.find_map(|a| match a.strip_prefix(arg)? {
+            "" => Some(String::new()),
+            rest => rest.strip_prefix('=').map(str::to_string),
+        })
+    })?;
+    value.trim().parse().ok()
+}
+
+// 开关：设置了环境变量 env（任意值）或使用了 arg 参数时开启
+fn switch(env: &str, arg: &str) -> bool {
+    flag::<String>(env, arg).is_some()
+}
+
+// 鸟的模型缩放比例，碰撞盒按同样的比例缩放
+// 保存在 Settings 中，默认为 BIRB_SCALE
+// 设置环境变量 TYPEY_BIRB_SCALE 或使用 --scale=0.3 参数在本次运行中覆盖
+struct BirbScale(f32);
+impl Default for BirbScale {
+    fn default() -> Self {
+        Self(BIRB_SCALE)
+    }
+}
+impl BirbScale {
+    fn new(settings: &Settings) -> Self {
+        let scale = flag("TYPEY_BIRB_SCALE", "--scale").unwrap_or(settings.birb_scale);
+        Self(if scale > 0. { scale } else { BIRB_SCALE })
+    }
+
+    // 相对于默认缩放比例的倍数
+    fn factor(&self) -> f32 {
+        self.0 / BIRB_SCALE
+    }
+}
+const BIRB_SCALE: f32 = 0.25;
+
 // 同上
 // 加载字体资源
 #[derive(AssetCollection)]
@@ -73,20 +205,33 @@ struct AudioAssets {
     crash: Handle<AudioSource>,
     #[asset(path = "bump.ogg")]
     bump: Handle<AudioSource>,
+    #[asset(path = "key.wav")]
+    key: Handle<AudioSource>,
+}
+
+// 同上，加载纹理资源
+#[derive(AssetCollection)]
+struct TextureAssets {
+    // 圆柱体的纹理，左右边缘可以无缝拼接
+    #[asset(path = "pipe.png")]
+    pipe: Handle<Image>,
+    // 远景的山丘和云朵，左右边缘同样可以无缝拼接
+    #[asset(path = "hills.png")]
+    hills: Handle<Image>,
+    #[asset(path = "clouds.png")]
+    clouds: Handle<Image>,
 }
 
-//  bevy::audio::AudioSink 用于控制声音资源
-struct MusicController(Handle<AudioSink>);
 
 
 // 定义 App 状态
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum AppState {
+pub enum AppState {
     Loading, // 正在加载
     StartScreen, // 开始屏幕
+    Settings, // 设置，覆盖在开始屏幕之上
     Playing, // 游戏中
-    #[cfg(feature = "inspector")]
-    Paused, // 暂停，用于调试
+    Paused, // 暂停，覆盖在游戏之上
     EndScreen, // 结束屏幕
 }
 
@@ -97,12 +242,19 @@ enum AppState {
 struct Birb;
 // 定义 竞争的金色 Bird 组件，非玩家操控
 #[derive(Component)]
-struct Rival;
+struct Rival {
+    offset: Vec3, // 在鸟群中的位置
+    phase: f32,   // 上下浮动的相位，每帧累加，与程序运行了多久无关
+}
 
 // 定义目标位置组件
 #[derive(Component)]
 struct TargetPosition(Vec3); // Vec3 代表 3D 向量
 
+// 上一帧碰撞检测时鸟的位置，用于扫掠碰撞检测
+#[derive(Component)]
+struct PreviousPosition(Vec3);
+
 // 定义当前 z 轴旋转角度组件
 // 本游戏 3D 模型只需要 x和z 轴变换
 #[derive(Component)]
@@ -110,17 +262,48 @@ struct CurrentRotationZ(f32);
 
 
 // 定义鸟的动作
+// 除了打字输入，外部控制器（手柄、语音识别等）也可以直接发送动作来控制鸟，不需要经过 TypingTarget：
+//     fn voice_control(mut actions: EventWriter<Action>) {
+//         actions.send(Action::BirbUp);
+//     }
+// 游戏中由 update_target_position 处理 BirbUp/BirbDown，移动鸟并播放拍翅膀或碰到边界的音效
+// 已有的变体保持不变，以后只增加新的变体
 #[derive(Clone, Debug)]
 pub enum Action {
     BadFlap, // 碰撞以后停止摆动翅膀
+    KeyHit { char: char }, // 输对一个字母
     BirbUp, // 鸟向上飞
     BirbDown, // 鸟向下飞
     NewWord(Entity), // 新的单词出现
+    WordExpired, // 单词倒计时结束
     IncScore(u32), // 分数增量
     Start, // 开始
     Retry, // 重试
+    Settings, // 打开设置
+    SetDifficulty(Difficulty), // 选择难度
+    SetCategory(&'static str), // 选择单词分类
+    TogglePractice, // 切换练习模式
+    NextSkin, // 切换鸟的模型
+    StartZen, // 开始禅模式
+    Quit, // 打开退出确认
+    ConfirmQuit, // 确认退出游戏
+    CancelQuit, // 取消退出
+    BeatRival, // 本局第一次超过竞争鸟
+    Milestone(u32), // 分数第一次达到某个里程碑
+}
+
+// 状态切换事件，进入一个状态时发送，包括从暂停、设置等覆盖状态返回
+// 外部系统可以监听它，不需要自己轮询 State<AppState>
+#[derive(Clone, Debug)]
+pub struct GameStateChanged {
+    pub from: Option<AppState>, // 之前的状态，第一次进入 Loading 时为 None
+    pub to: AppState,
 }
 
+// 上一次发送 GameStateChanged 时的状态
+#[derive(Default)]
+struct LastAppState(Option<AppState>);
+
 // 障碍物（圆柱体）组件
 #[derive(Component)]
 struct Obstacle;
@@ -131,15 +314,269 @@ struct ScoreCollider;
 #[derive(Component)]
 struct ObstacleCollider;
 // 用于碰撞检测中用于标记未碰撞障碍物
+// 练习模式下撞过的障碍物也会标记，同一个障碍物只扣一次分
 #[derive(Component)]
 struct Used;
 
 // Resources
 // 资源，乃全局变量
+
+// 障碍物共用的网格，在 setup 中创建一次，避免每次生成障碍物都重新构建
+struct ObstacleMeshes {
+    flange: Handle<Mesh>, // 圆柱体盖子
+}
+// 障碍物共用的绿色材质
+struct ObstacleMaterial(Handle<StandardMaterial>);
+// 按空隙松紧给障碍物上色时使用的材质，从红（最窄）到绿（最宽），在 setup 中创建一次
+struct GapColorMaterials(Vec<Handle<StandardMaterial>>);
+const GAP_COLOR_STEPS: usize = 5;
+// 是否按空隙松紧给障碍物上色，默认关闭，保持原来全部绿色的样子
+// 保存在 Settings 中，设置环境变量 TYPEY_BIRB_GAP_COLORS 或使用 --gap-colors 参数在本次运行中开启
+#[derive(Default)]
+struct GapColors(bool);
+impl GapColors {
+    fn new(settings: &Settings) -> Self {
+        Self(settings.gap_colors || switch("TYPEY_BIRB_GAP_COLORS", "--gap-colors"))
+    }
+}
+// 空隙大小对应的颜色档位，0 为最窄
+// 最宽按 next_shape 放大后的基础空隙计算
+fn gap_color_step(gap_size: f32, base_gap: f32) -> usize {
+    let widest = base_gap * WIDE_GAP_SCALE;
+    let t = ((gap_size - MIN_GAP_SIZE) / (widest - MIN_GAP_SIZE)).clamp(0., 1.);
+    (t * (GAP_COLOR_STEPS - 1) as f32).round() as usize
+}
+// 障碍物外形是否多样化：开启后部分障碍物为圆锥体或胶囊体，默认全部为圆柱体
+struct ObstacleVariety(bool);
+impl ObstacleVariety {
+    // 设置环境变量 TYPEY_BIRB_VARIETY 或使用 --variety 参数开启
+    fn from_env() -> Self {
+        Self(switch("TYPEY_BIRB_VARIETY", "--variety"))
+    }
+}
+
+// 鸟的碰撞盒大小，比模型实际的 Aabb 略小一些
+// 设置环境变量 TYPEY_BIRB_HITBOX=strict 或使用 --strict-hitbox 参数使用严格的碰撞盒
+// 游戏中按 F3 在两种预设之间切换
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BirbHitbox {
+    Generous, // 宽松：默认，擦到头顶或肚皮不算碰撞
+    Strict,   // 严格：接近模型实际大小
+}
+impl BirbHitbox {
+    fn from_env() -> Self {
+        let strict = flag::<String>("TYPEY_BIRB_HITBOX", "--hitbox")
+            .is_some_and(|s| s.eq_ignore_ascii_case("strict"));
+        if strict || switch("TYPEY_BIRB_STRICT_HITBOX", "--strict-hitbox") {
+            Self::Strict
+        } else {
+            Self::Generous
+        }
+    }
+
+    fn toggle(self) -> Self {
+        match self {
+            Self::Generous => Self::Strict,
+            Self::Strict => Self::Generous,
+        }
+    }
+
+    fn half_extents(self) -> Vec3A {
+        match self {
+            Self::Generous => Vec3A::new(0.2, 0.3, 0.25),
+            Self::Strict => Vec3A::new(0.25, 0.35, 0.3),
+        }
+    }
+
+    fn aabb(self, scale: &BirbScale) -> Aabb {
+        Aabb {
+            center: Vec3A::splat(0.),
+            half_extents: self.half_extents() * scale.factor(),
+        }
+    }
+}
+
+// 擦边飞过障碍物后的无敌时间，避免连续两个盖子在同一帧结束游戏
+// 计时结束前忽略与障碍物的轻微碰撞
+struct Grace(Timer);
+impl Default for Grace {
+    fn default() -> Self {
+        // 初始状态为已结束，没有无敌时间
+        let mut timer = Timer::from_seconds(GRACE_SECONDS, false);
+        timer.set_elapsed(timer.duration());
+        Self(timer)
+    }
+}
+impl Grace {
+    fn active(&self) -> bool {
+        !self.0.finished()
+    }
+}
+
+// 游戏时间的相对速度，擦边飞过障碍物时短暂放慢，制造慢动作效果
+// bevy 0.8 的 Time 不能调整速度，障碍物、地面、竞争鸟等移动都通过 delta_seconds 换算成游戏时间
+struct TimeScale {
+    relative_speed: f32,
+    slow_motion: Timer, // 慢动作的剩余时间，按真实时间计时
+}
+impl Default for TimeScale {
+    fn default() -> Self {
+        // 初始状态为已结束，正常速度
+        let mut timer = Timer::from_seconds(SLOW_MOTION_SECONDS, false);
+        timer.set_elapsed(timer.duration());
+        Self {
+            relative_speed: 1.,
+            slow_motion: timer,
+        }
+    }
+}
+impl TimeScale {
+    // 本帧经过的游戏时间
+    fn delta_seconds(&self, time: &Time) -> f32 {
+        time.delta_seconds() * self.relative_speed
+    }
+
+    fn slow_down(&mut self) {
+        self.relative_speed = SLOW_MOTION_SPEED;
+        self.slow_motion.reset();
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        if self.slow_motion.tick(delta).finished() {
+            self.relative_speed = 1.;
+        }
+    }
+}
+
 #[derive(Default)]
 struct Score(u32); // 分数
+
+// 游戏中每次输错扣除的分数，默认为 0 不扣分
+// 设置环境变量 TYPEY_BIRB_MISTYPE_PENALTY 或使用 --mistype-penalty 参数修改
+struct MistypePenalty(u32);
+impl MistypePenalty {
+    fn from_env() -> Self {
+        Self(flag("TYPEY_BIRB_MISTYPE_PENALTY", "--mistype-penalty").unwrap_or(0))
+    }
+}
+
+// 撞到障碍物时的反应
+// Die：直接结束游戏；Bounce：扣分并被弹开，撞到 BOUNCE_MAX_HITS 次才结束
+// Practice：练习模式，扣分并被弹开，永远不会结束
+// 设置环境变量 TYPEY_BIRB_HIT_REACTION=bounce 或使用 --bounce 参数选择 Bounce
+// 在开始屏幕上输入 practice 切换练习模式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HitReaction {
+    Die,
+    Bounce,
+    Practice,
+}
+impl HitReaction {
+    fn from_env() -> Self {
+        let value: String = flag("TYPEY_BIRB_HIT_REACTION", "--hit-reaction").unwrap_or_default();
+        match value.to_lowercase().as_str() {
+            "practice" => Self::Practice,
+            "bounce" => Self::Bounce,
+            _ if switch("TYPEY_BIRB_BOUNCE", "--bounce") => Self::Bounce,
+            _ => Self::Die,
+        }
+    }
+
+    // 这次撞击是否结束游戏，hits 为包括这次在内的撞击次数
+    fn is_fatal(self, hits: u32) -> bool {
+        match self {
+            Self::Die => true,
+            Self::Bounce => hits >= BOUNCE_MAX_HITS,
+            Self::Practice => false,
+        }
+    }
+}
+
+// 本局撞到障碍物的次数，Bounce 模式据此判断是否结束游戏
+#[derive(Default)]
+struct Hits(u32);
+
+// Bounce 模式下第几次撞击结束游戏
+const BOUNCE_MAX_HITS: u32 = 3;
+
+// 游戏模式，在开始屏幕上输入 zen 开始一局禅模式，reset 时回到普通模式
+// 禅模式：没有竞争鸟，障碍物稀疏、空隙很大，撞到也不会结束，不显示分数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Normal,
+    Zen,
+}
+impl GameMode {
+    pub fn is_zen(self) -> bool {
+        self == Self::Zen
+    }
+}
+
+// 禅模式下障碍物间距和空隙大小的倍数
+const ZEN_SPACING_SCALE: f32 = 2.;
+const ZEN_GAP_SCALE: f32 = 1.5;
+
+// 没有结束游戏的撞击扣除的分数和鸟被弹开的距离
+const HIT_PENALTY: u32 = 5;
+const HIT_KNOCKBACK: f32 = 0.5;
+
+// 输对字母时按字母演奏音符，输入单词就像弹奏一段旋律
+// 设置环境变量 TYPEY_BIRB_NO_MELODY 或使用 --no-melody 参数关闭，改为普通的按键音
+struct MelodicKeys(bool);
+impl MelodicKeys {
+    fn from_env() -> Self {
+        Self(!switch("TYPEY_BIRB_NO_MELODY", "--no-melody"))
+    }
+}
+
+// 穿过一个障碍物的空隙得到的分数，默认为 2
+// 设置环境变量 TYPEY_BIRB_PASS_SCORE 或使用 --pass-score 参数修改
+struct PassScore(u32);
+impl PassScore {
+    fn from_env() -> Self {
+        Self(flag("TYPEY_BIRB_PASS_SCORE", "--pass-score").unwrap_or(2))
+    }
+}
 #[derive(Default)]
 struct DistanceToSpawn(f32); // 生成障碍物之间距离
+
+// 本局飞行的总距离，在 obstacle_movement 中按速度累加，reset 时清零
+// 结束屏幕显示，也可用于按距离调整难度或与竞争鸟比较
+#[derive(Default)]
+pub struct Distance(pub f32);
+
+// 鸟群中竞争鸟的数量
+struct RivalCount(usize);
+impl Default for RivalCount {
+    fn default() -> Self {
+        Self(DEFAULT_RIVAL_COUNT)
+    }
+}
+
+// 分数的里程碑，从小到大排列，达到时显示庆祝的横幅，结束屏幕也按它们选择评语
+pub const SCORE_MILESTONES: [u32; 3] = [200, 400, 1000];
+
+// 本局已经达到的里程碑个数，reset 时重置
+#[derive(Default)]
+struct Milestones(usize);
+
+// 与竞争鸟的比赛
+// 竞争鸟以固定速度飞行；玩家的有效距离为飞行距离加上分数带来的加成
+// 打字又快又准的玩家会超过竞争鸟，反之则落在后面
+#[derive(Default)]
+struct RivalRace {
+    rival_distance: f32,  // 竞争鸟飞行的距离
+    player_distance: f32, // 玩家的有效距离
+    lead: f32,            // 竞争鸟领先玩家的距离，为负时玩家领先
+    overtaken: bool,      // 本局是否超过过竞争鸟，已发送 Action::BeatRival
+}
+impl RivalRace {
+    // 玩家当前是否领先竞争鸟
+    fn beaten(&self) -> bool {
+        self.lead < 0.
+    }
+}
 struct ObstacleSpacing(f32); // 障碍物起始空间距离，默认为 12.0
 impl Default for ObstacleSpacing {
     fn default() -> Self {
@@ -147,35 +584,232 @@ impl Default for ObstacleSpacing {
     }
 }
 
+// 分数越高障碍物之间的距离越小：每得一分缩小的距离，以及缩小的下限
+// 设置环境变量 TYPEY_BIRB_SPACING_RATE、TYPEY_BIRB_SPACING_FLOOR 或使用 --spacing-rate、--spacing-floor 参数修改
+struct SpacingRamp {
+    per_point: f32,
+    floor: f32,
+}
+impl Default for SpacingRamp {
+    fn default() -> Self {
+        Self {
+            per_point: 0.02,
+            floor: 8.,
+        }
+    }
+}
+impl SpacingRamp {
+    fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            per_point: flag("TYPEY_BIRB_SPACING_RATE", "--spacing-rate")
+                .filter(|v: &f32| *v >= 0.)
+                .unwrap_or(default.per_point),
+            floor: flag("TYPEY_BIRB_SPACING_FLOOR", "--spacing-floor")
+                .filter(|v: &f32| *v > 0.)
+                .unwrap_or(default.floor),
+        }
+    }
+
+    // 按分数计算障碍物之间的距离，起始距离比下限还小时保持起始距离
+    fn spacing(&self, start: f32, score: u32) -> f32 {
+        (start - score as f32 * self.per_point).max(self.floor.min(start))
+    }
+}
+
 // 速度
 struct Speed {
     current: f32,
     max: f32,
+    ramp: f32, // 每秒增加的速度
 }
 impl Default for Speed {
+    fn default() -> Self {
+        Self::new(&SpeedConfig::default())
+    }
+}
+impl Speed {
+    fn new(config: &SpeedConfig) -> Self {
+        Self {
+            current: config.start,
+            max: config.max,
+            ramp: config.ramp,
+        }
+    }
+
+    fn accelerate(&mut self, seconds: f32) {
+        self.current = (self.current + self.ramp * seconds).min(self.max);
+    }
+}
+
+// 每秒增加的速度，持续加速直到最高速度
+// 与原来每个障碍物加速 0.1 相比，普通难度下加速到最高速度的时间大致相同
+const SPEED_RAMP: f32 = 0.025;
+
+// 本局的起始速度、最高速度和每秒加速，进入游戏时按难度设置，重置时据此恢复 Speed
+// 设置环境变量 TYPEY_BIRB_START_SPEED、TYPEY_BIRB_MAX_SPEED、TYPEY_BIRB_SPEED_RAMP
+// 或使用 --start-speed、--max-speed、--speed-ramp 参数可以覆盖
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedConfig {
+    pub start: f32,
+    pub max: f32,
+    pub ramp: f32,
+}
+impl Default for SpeedConfig {
     fn default() -> Self {
         Self {
-            current: 2.,
+            start: 2.,
             max: 4.4,
+            ramp: SPEED_RAMP,
         }
     }
 }
-impl Speed {
-    fn increase(&mut self, amt: f32) {
-        self.current = (self.current + amt).min(self.max);
+impl SpeedConfig {
+    pub fn for_difficulty(difficulty: Difficulty) -> Self {
+        let var =
+            |env, arg, default: f32| flag(env, arg).filter(|v: &f32| *v >= 0.).unwrap_or(default);
+        let defaults = Self::default();
+        let start = var("TYPEY_BIRB_START_SPEED", "--start-speed", defaults.start);
+        Self {
+            start,
+            // 最高速度不低于起始速度
+            max: var(
+                "TYPEY_BIRB_MAX_SPEED",
+                "--max-speed",
+                difficulty.max_speed(),
+            )
+            .max(start),
+            ramp: var("TYPEY_BIRB_SPEED_RAMP", "--speed-ramp", defaults.ramp),
+        }
+    }
+}
+
+// 每输对一个字母鸟上下移动的距离，由难度决定
+struct FlapStep(f32);
+impl Default for FlapStep {
+    fn default() -> Self {
+        Self(0.25)
     }
 }
 
+// 鸟每秒下沉的距离，由难度决定，为 0 时不下沉
+#[derive(Default)]
+struct Gravity(f32);
+
 // bird 起始坐标
 const BIRB_START_Y: f32 = 3.;
 // bird 上下坐标范围
 const BIRB_MIN_Y: f32 = 0.9;
 const BIRB_MAX_Y: f32 = 6.3;
 
-// 上下障碍物之间空隙大小和坐标范围
-const GAP_SIZE: f32 = 2.;
+// 上下障碍物之间空隙的坐标范围，空隙大小由 GapSize 决定
 const GAP_START_MIN_Y: f32 = 0.5;
-const GAP_START_MAX_Y: f32 = 6.7 - GAP_SIZE;
+// 空隙顶部的最高位置
+const GAP_CEILING_Y: f32 = 6.7;
+
+// 滚动方向：默认鸟向右飞，镜像模式下整个场景左右翻转，鸟向左飞
+// 保存在 Settings 中，方便左手操作或镜像显示
+// 设置环境变量 TYPEY_BIRB_MIRROR 或使用 --mirror 参数在本次运行中开启
+// 游戏逻辑始终按向右飞计算，水平方向的位置和移动在写入 Transform 时乘以这个系数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollDir(pub f32);
+impl Default for ScrollDir {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+impl ScrollDir {
+    fn new(settings: &Settings) -> Self {
+        let mirrored = settings.mirrored || switch("TYPEY_BIRB_MIRROR", "--mirror");
+        Self(if mirrored { -1. } else { 1. })
+    }
+
+    // 翻转朝向 +x 的旋转：先按 x = 0 的平面镜像，再转半圈让左右对称的模型朝向 -x
+    pub fn rotation(self, rotation: Quat) -> Quat {
+        if self.0 > 0. {
+            return rotation;
+        }
+        Quat::from_xyzw(rotation.x, -rotation.y, -rotation.z, rotation.w)
+            * Quat::from_rotation_y(std::f32::consts::PI)
+    }
+
+    // 翻转位置和旋转，用于开始、结束屏幕上摆放的模型
+    pub fn transform(self, mut transform: Transform) -> Transform {
+        transform.translation.x *= self.0;
+        transform.rotation = self.rotation(transform.rotation);
+        transform
+    }
+}
+
+// 本局上下障碍物之间空隙的基本大小，进入游戏时按难度设置
+// 每个障碍物在此基础上随机变化，并随飞行距离缩小
+// 设置环境变量 TYPEY_BIRB_GAP_SIZE 或使用 --gap-size 参数可以覆盖难度决定的大小
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GapSize(pub f32);
+impl Default for GapSize {
+    fn default() -> Self {
+        Self::new(Difficulty::default().gap_size())
+    }
+}
+impl GapSize {
+    // 限制在 MIN_GAP_SIZE 和空隙坐标范围之间，空隙底部在最低处时顶部也不会超过上限
+    pub fn new(size: f32) -> Self {
+        Self(size.clamp(MIN_GAP_SIZE, GAP_CEILING_Y - GAP_START_MIN_Y))
+    }
+
+    pub fn for_difficulty(difficulty: Difficulty) -> Self {
+        let size =
+            flag("TYPEY_BIRB_GAP_SIZE", "--gap-size").unwrap_or_else(|| difficulty.gap_size());
+        Self::new(size)
+    }
+
+    // 空隙底部的坐标范围，空隙顶部不超过 GAP_CEILING_Y
+    pub fn start_range(&self) -> Range<f32> {
+        GAP_START_MIN_Y..(GAP_CEILING_Y - self.0)
+    }
+}
+
+// 圆柱体默认半径，每个障碍物在此基础上随机变化
+const PIPE_RADIUS: f32 = 0.75;
+// 圆柱体盖子的高度和半径，盖子半径随圆柱体半径等比例缩放
+const FLANGE_HEIGHT: f32 = 0.4;
+const FLANGE_RADIUS: f32 = 0.8;
+
+// 空隙的最小值：鸟的碰撞盒高度加上一次拍打翅膀的移动距离，再留一些余量
+// 鸟只能停在间隔固定的位置上，空隙小于这个值时可能无法通过
+const MIN_GAP_SIZE: f32 = 1.2;
+
+// 飞得越远空隙越小：每飞行一个单位距离空隙缩小的比例，以及缩小的下限
+// 最终的空隙仍然不会小于 MIN_GAP_SIZE
+const GAP_RAMP_PER_DISTANCE: f32 = 0.0002;
+const GAP_RAMP_MIN_SCALE: f32 = 0.7;
+
+// 分数达到该值后开始出现上下移动的障碍物
+const MOVING_OBSTACLE_MIN_SCORE: u32 = 100;
+// 移动障碍物的最大振幅，太小的振幅没有意义
+const MOVING_OBSTACLE_MAX_AMPLITUDE: f32 = 0.8;
+const MOVING_OBSTACLE_MIN_AMPLITUDE: f32 = 0.2;
+
+// 鸟群中竞争鸟的默认数量
+const DEFAULT_RIVAL_COUNT: usize = 3;
+
+// 竞争鸟的速度，以及每一分折算成的有效距离
+const RIVAL_SPEED: f32 = 5.;
+const RIVAL_SCORE_DISTANCE: f32 = 0.5;
+
+// 擦边的判定距离：鸟的碰撞盒向外扩大这么多后与障碍物重合，就算擦边飞过
+const NEAR_MISS_MARGIN: f32 = 0.1;
+// 擦边后的无敌时间（秒）
+const GRACE_SECONDS: f32 = 0.25;
+// 无敌时间内仍然算作碰撞的嵌入深度，防止直接穿过障碍物
+const GRACE_MAX_DEPTH: f32 = 0.15;
+// 擦边后慢动作的相对速度和持续时间（秒）
+const SLOW_MOTION_SPEED: f32 = 0.4;
+const SLOW_MOTION_SECONDS: f32 = 0.5;
+
+// 障碍物生成位置和回收位置
+const OBSTACLE_SPAWN_X: f32 = 38.;
+const OBSTACLE_DESPAWN_X: f32 = -30.;
 
 fn main() {
     let mut app = App::new();
@@ -185,7 +819,9 @@ fn main() {
             .continue_to_state(AppState::StartScreen)
             .with_collection::<GltfAssets>()
             .with_collection::<FontAssets>()
-            .with_collection::<AudioAssets>(),
+            .with_collection::<AudioAssets>()
+            .with_collection::<TextureAssets>()
+            .with_collection::<ground::GroundAssets>(),
     );
 
     // 插入窗口描述
@@ -207,28 +843,92 @@ fn main() {
     #[cfg(feature = "inspector")]
     {
         app.add_plugin(WorldInspectorPlugin::new());
-        app.add_system_set(SystemSet::on_update(AppState::Paused).with_system(pause));
-        app.add_system_set(SystemSet::on_update(AppState::Playing).with_system(pause));
+        app.add_plugin(DebugLinesPlugin::default());
+        app.add_system(draw_colliders);
     }
 
     // 设置初始化loading状态
     app.add_state(AppState::Loading);
 
+    // 随机种子：设置环境变量 TYPEY_BIRB_SEED 可以得到每局相同的关卡
+    // 开启每日挑战时使用由日期计算出的种子
+    let daily = DailyChallenge::from_env();
+    let seed = if daily.0 {
+        Seed::daily()
+    } else {
+        Seed::from_env()
+    };
+    if let Some(seed) = seed.0 {
+        info!("Using seed {}", seed);
+    }
+    // 所有随机数的来源，每局开始时按本局的种子重新创建
+    let game_rng = GameRng::from_seed(seed);
+    let bag = NextGapBag::with_seed(
+        GapSize::default().start_range(),
+        BIRB_START_Y,
+        game_rng.stream_seed(RngStream::Gaps),
+    );
+
     // 初始化资源：分数、速度、障碍物距离和起始空间
     app.init_resource::<Score>()
         .init_resource::<Speed>()
+        .init_resource::<SpeedConfig>()
+        .init_resource::<FlapStep>()
+        .init_resource::<Gravity>()
         .init_resource::<DistanceToSpawn>()
+        .init_resource::<Distance>()
+        .init_resource::<RivalRace>()
+        .init_resource::<RivalCount>()
+        .init_resource::<QuitKey>()
+        .init_resource::<RetryHotkeyDelay>()
+        .insert_resource(MistypePenalty::from_env())
+        .insert_resource(PassScore::from_env())
+        .insert_resource(MelodicKeys::from_env())
+        .insert_resource(HitReaction::from_env())
+        .init_resource::<Hits>()
+        .init_resource::<Milestones>()
+        .init_resource::<FloatTime>()
+        .init_resource::<GameMode>()
+        .init_resource::<GapSize>()
+        .insert_resource(BirbHitbox::from_env())
+        .init_resource::<BirbScale>()
+        .init_resource::<Grace>()
+        .init_resource::<TimeScale>()
         .init_resource::<ObstacleSpacing>()
-        .insert_resource(NextGapBag::new(
-            GAP_START_MIN_Y..GAP_START_MAX_Y,
-            BIRB_START_Y,
-        ))
-        .add_event::<Action>();
-
-    // 增加 Plugin ： 打字输入处理、UI和背景
+        .insert_resource(SpacingRamp::from_env())
+        .insert_resource(bag)
+        .insert_resource(game_rng)
+        .insert_resource(seed)
+        .insert_resource(daily)
+        .insert_resource(ObstacleVariety::from_env())
+        .init_resource::<GapColors>()
+        .init_resource::<ScrollDir>()
+        .add_event::<Action>()
+        .add_event::<GameStateChanged>()
+        .init_resource::<LastAppState>();
+
+    // 增加 Plugin ： 打字输入处理、UI、背景、摄像机、声音、存档、难度、录像、本地化、快照和加载检查
     app.add_plugin(crate::typing::TypingPlugin)
         .add_plugin(crate::ui::UiPlugin)
-        .add_plugin(crate::ground::GroundPlugin);
+        .add_plugin(crate::ground::GroundPlugin)
+        .add_plugin(crate::camera::CameraPlugin)
+        .add_plugin(crate::audio::AudioPlugin)
+        .add_plugin(crate::save::SavePlugin)
+        .add_plugin(crate::difficulty::DifficultyPlugin)
+        .add_plugin(crate::particles::ParticlesPlugin)
+        .add_plugin(crate::sky::SkyPlugin)
+        .add_plugin(crate::background::BackgroundPlugin)
+        .add_plugin(crate::recording::RecordingPlugin)
+        .add_plugin(crate::keylog::KeylogPlugin)
+        .add_plugin(crate::locale::LocalePlugin)
+        .add_plugin(crate::snapshot::SnapshotPlugin)
+        .add_plugin(crate::loading::LoadingPlugin);
+
+    // 读取设置以后再摆放摄像机，镜像模式保存在设置中
+    app.add_system_set(
+        SystemSet::on_exit(AppState::Loading)
+            .with_system(apply_settings.after("load_settings").before("setup")),
+    );
 
     // 将 SystemSet 增加到 update 阶段（stages）
     // stage 用于 Bevy 底层调度 Schedule, Schedule 以线性顺序来执行其中的各个 stage
@@ -236,12 +936,13 @@ fn main() {
     // SystemSet 和 App 状态相关，内部是一个基于栈的状态机
     // 这里告诉 App 在 loading 的状态结束（ `exit`） 时执行一次 setup 
     // setup 在下面定义，用于设置 摄像机
-    app.add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup))
+    app.add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup.label("setup")))
         .add_system_set(
             // 在 StartScreen 开始的时候可能执行的动作
             //  spawn_bird （创建鸟）和 开启屏幕音乐
             SystemSet::on_enter(AppState::StartScreen)
                 .with_system(spawn_birb)
+                .with_system(reset_float_time)
                 .with_system(start_screen_music),
         )
         .add_system_set(
@@ -254,61 +955,145 @@ fn main() {
             // 生成竞争对手（spawn_rival） 并开启游戏音乐
             SystemSet::on_enter(AppState::Playing)
                 .with_system(spawn_rival)
+                .with_system(fill_obstacle_pool.after("apply_difficulty"))
                 .with_system(game_music),
         )
         .add_system_set(
             // 在 AppState::Playing 状态 每次更新的时候可能执行的动作
             SystemSet::on_update(AppState::Playing)
+                // 鸟随时间下沉
+                .with_system(gravity.before("movement"))
                 // 移动鸟
-                .with_system(movement)
+                .with_system(movement.label("movement"))
+                // 更新与竞争鸟的比赛
+                .with_system(rival_race.label("rival_race").after("obstacle_movement"))
+                // 分数达到里程碑时庆祝
+                .with_system(score_milestones)
                 // 移动竞争对手
                 .with_system(rival_movement)
-                //  碰撞检测
-                .with_system(collision)
+                //  碰撞检测，在鸟和障碍物本帧移动之后进行
+                .with_system(
+                    collision
+                        .label("collision")
+                        .after("movement")
+                        .after("obstacle_movement"),
+                )
+                // 擦边后的慢动作按真实时间结束
+                .with_system(slow_motion.after("collision"))
+                // 逐渐加速，在所有按速度移动的系统之前，同一帧内速度一致
+                .with_system(
+                    accelerate
+                        .before("obstacle_movement")
+                        .before("ground_movement")
+                        .before("background_movement"),
+                )
                 // 移动障碍物（产生小鸟向前飞行的效果）
-                .with_system(obstacle_movement)
+                .with_system(obstacle_movement.label("obstacle_movement"))
+                // 分数提高后缩小障碍物之间的距离
+                .with_system(tighten_spacing)
                 // 生成新的障碍物
-                .with_system(spawn_obstacle)
+                .with_system(spawn_obstacle.label("spawn_obstacle"))
+                // 上下移动障碍物
+                .with_system(moving_obstacle_movement)
                 // 更新目标位置
                 .with_system(update_target_position)
                 // 更新分数
                 .with_system(update_score)
                 // 播放碰撞失败音乐
-                .with_system(bad_flap_sound),
+                .with_system(bad_flap_sound)
+                // 播放按键音
+                .with_system(key_hit_sound),
         )
         .add_system_set(
             // 在 AppState::StartScreen 状态每次更新的时候可能执行的动作
             // 执行 start_game 和 bad_flap_sound
             SystemSet::on_update(AppState::StartScreen)
                 .with_system(start_game)
-                .with_system(bad_flap_sound),
+                .with_system(open_settings)
+                .with_system(toggle_practice)
+                .with_system(change_skin)
+                .with_system(quit_game)
+                .with_system(bad_flap_sound)
+                .with_system(key_hit_sound),
         )
         .add_system_set(
             // 在 AppState::EndScreen 状态更新的时候可能执行的动作
             SystemSet::on_update(AppState::EndScreen)
                 // 移动竞争鸟角色
                 .with_system(rival_movement)
+                // 按 Enter 或 R 立即重试，在 keyboard 之前，输入名字缩写的最后一个字母时不会触发
+                .with_system(retry_hotkey.label("retry_hotkey").before("keyboard"))
                 // 重试游戏
-                .with_system(retry_game)
+                .with_system(retry_game.after("retry_hotkey"))
                 // 播放碰撞失败音乐
-                .with_system(bad_flap_sound),
+                .with_system(bad_flap_sound)
+                .with_system(key_hit_sound),
+        )
+        // 暂停和继续
+        .add_system_set(SystemSet::on_update(AppState::Playing).with_system(pause))
+        .add_system_set(SystemSet::on_update(AppState::Paused).with_system(pause))
+        // 窗口失去焦点时自动暂停
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing).with_system(auto_pause.after(pause)),
         )
+        // 放弃本局，回到开始屏幕
+        .add_system_set(SystemSet::on_update(AppState::Playing).with_system(quit_to_menu))
+        .add_system_set(SystemSet::on_update(AppState::Paused).with_system(quit_to_menu))
+        // 切换鸟的碰撞盒预设，并同步到鸟的 Aabb 组件
+        .add_system(toggle_birb_hitbox)
+        .add_system(apply_birb_hitbox.after("movement").before("collision"))
+        // 换了模型以后立即替换鸟的场景，开始屏幕上可以直接预览
+        .add_system(apply_birb_skin)
         // 在 AppState::EndScreen 状态结束的时候执行 reset
         .add_system_set(SystemSet::on_exit(AppState::EndScreen).with_system(reset))
-        .run();
+        .add_system_set(SystemSet::on_enter(AppState::EndScreen).with_system(arm_retry_hotkey))
+        .add_system(log_state_changes);
+
+    // 进入每个状态时发送 GameStateChanged，从覆盖状态返回时也发送
+    for state in [
+        AppState::Loading,
+        AppState::StartScreen,
+        AppState::Settings,
+        AppState::Playing,
+        AppState::Paused,
+        AppState::EndScreen,
+    ] {
+        app.add_system_set(SystemSet::on_enter(state.clone()).with_system(send_state_changed))
+            .add_system_set(SystemSet::on_resume(state).with_system(send_state_changed));
+    }
+
+    app.run();
 }
 
-// 用于调试
-#[cfg(feature = "inspector")]
+fn send_state_changed(
+    state: Res<State<AppState>>,
+    mut last: ResMut<LastAppState>,
+    mut events: EventWriter<GameStateChanged>,
+) {
+    let to = state.current().clone();
+    let from = last.0.replace(to.clone());
+    if from.as_ref() != Some(&to) {
+        events.send(GameStateChanged { from, to });
+    }
+}
+
+fn log_state_changes(mut events: EventReader<GameStateChanged>) {
+    for e in events.iter() {
+        debug!("State changed from {:?} to {:?}", e.from, e.to);
+    }
+}
+
+// 按 Esc 暂停/继续
+// 暂停状态压在游戏状态之上，继续时不会重新执行 on_enter(Playing)，音乐和竞争鸟都不会重新生成
 fn pause(mut keyboard: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
     if keyboard.just_pressed(KeyCode::Escape) {
         match state.current() {
             AppState::Paused => {
-                state.set(AppState::Playing).unwrap();
+                state.pop().unwrap();
                 keyboard.clear();
             }
             AppState::Playing => {
-                state.set(AppState::Paused).unwrap();
+                state.push(AppState::Paused).unwrap();
                 keyboard.clear();
             }
             _ => {}
@@ -316,18 +1101,138 @@ fn pause(mut keyboard: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>
     }
 }
 
+// 窗口失去焦点（如切换到其他窗口）时暂停，只在游戏中生效
+// 重新获得焦点后停留在暂停界面，由玩家按 Esc 继续
+fn auto_pause(mut events: EventReader<WindowFocused>, mut state: ResMut<State<AppState>>) {
+    if events.iter().any(|e| !e.focused) {
+        // 同一帧按下 Esc 时 pause 已经切换到暂停，忽略重复的切换
+        let _ = state.push(AppState::Paused);
+    }
+}
+
+// 每局结束后需要清理的实体：障碍物、鸟、竞争鸟、粒子和远景
+type RunEntities = Or<(
+    With<Obstacle>,
+    With<Birb>,
+    With<Rival>,
+    With<Particle>,
+    With<Background>,
+)>;
+
+// 放弃本局的按键，需要同时按住 Ctrl，默认为 Ctrl+Q（Esc 已用于暂停）
+// 单独的按键打字时会误触发，退格键更是输错以后常按的键
+struct QuitKey(KeyCode);
+impl Default for QuitKey {
+    fn default() -> Self {
+        Self(KeyCode::Q)
+    }
+}
+impl QuitKey {
+    fn just_pressed(&self, keyboard: &Input<KeyCode>) -> bool {
+        keyboard.any_pressed([KeyCode::LControl, KeyCode::RControl])
+            && keyboard.just_pressed(self.0)
+    }
+}
+
+// 游戏中或暂停时按下放弃键，回到开始屏幕
+// 与游戏结束后重试一样，通过 reset 清理障碍物、鸟、竞争鸟和粒子，并重置分数、速度等资源
+fn quit_to_menu(
+    commands: Commands,
+    query: Query<Entity, RunEntities>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    quit_key: Res<QuitKey>,
+    mut state: ResMut<State<AppState>>,
+    speed_config: Res<SpeedConfig>,
+) {
+    if !quit_key.just_pressed(&keyboard) {
+        return;
+    }
+
+    // 暂停状态压在游戏状态之上，replace 会把两者一起退出
+    if state.replace(AppState::StartScreen).is_ok() {
+        keyboard.clear();
+        reset(commands, query, speed_config);
+    }
+}
+
+// 用于调试，绘制所有碰撞盒
+// 与 collision 中一样把 Aabb 的中心加上实体的位置，保证画出来的就是实际参与碰撞的盒子
+#[cfg(feature = "inspector")]
+#[allow(clippy::type_complexity)]
+fn draw_colliders(
+    mut lines: ResMut<DebugLines>,
+    birb_query: Query<(&Aabb, &Transform), With<Birb>>,
+    collider_query: Query<
+        (&Aabb, &GlobalTransform, &Parent, Option<&ScoreCollider>),
+        (
+            Or<(With<ObstacleCollider>, With<ScoreCollider>)>,
+            Without<Used>,
+        ),
+    >,
+    parked_query: Query<(), With<Parked>>,
+) {
+    let mut draw = |aabb: &Aabb, translation: Vec3, color: Color| {
+        let min = Vec3::from(aabb.min()) + translation;
+        let max = Vec3::from(aabb.max()) + translation;
+
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+
+        for a in [false, true] {
+            for b in [false, true] {
+                lines.line_colored(corner(false, a, b), corner(true, a, b), 0., color);
+                lines.line_colored(corner(a, false, b), corner(a, true, b), 0., color);
+                lines.line_colored(corner(a, b, false), corner(a, b, true), 0., color);
+            }
+        }
+    };
+
+    for (aabb, transform) in birb_query.iter() {
+        draw(aabb, transform.translation, Color::YELLOW);
+    }
+
+    for (aabb, transform, parent, score) in collider_query.iter() {
+        // 对象池中闲置的障碍物不参与游戏，不画
+        if parked_query.get(parent.get()).is_ok() {
+            continue;
+        }
+
+        let color = if score.is_some() {
+            Color::BLUE
+        } else {
+            Color::RED
+        };
+        draw(aabb, transform.translation(), color);
+    }
+}
+
 // 重置游戏状态
 // bevy 中使用 Query 来查询 World 范围内的 实体和组件
 fn reset(
     mut commands: Commands,
     // 当前 Query类型参数代表使用 Entity ID 进行查询
-    // 并且使用 Or 过滤器判断拥有 Obstacle、Bird、Rival 组件的实体之一
+    // 并且使用 Or 过滤器（RunEntities）判断拥有 Obstacle、Bird、Rival 等组件的实体之一
     // Query 等价于 ECS 中的 SQL
-    query: Query<Entity, Or<(With<Obstacle>, With<Birb>, With<Rival>)>>,
+    query: Query<Entity, RunEntities>,
+    speed_config: Res<SpeedConfig>,
 ) {
     commands.insert_resource(Score::default());
-    commands.insert_resource(Speed::default());
+    commands.insert_resource(Speed::new(&speed_config));
     commands.insert_resource(DistanceToSpawn::default());
+    commands.insert_resource(Distance::default());
+    commands.insert_resource(RivalRace::default());
+    commands.insert_resource(Grace::default());
+    commands.insert_resource(TimeScale::default());
+    commands.insert_resource(Hits::default());
+    commands.insert_resource(Milestones::default());
+    commands.insert_resource(GameMode::default());
+    // 天空和光源回到白天
+    commands.insert_resource(sky::SkyCycle::default());
     commands.insert_resource(ObstacleSpacing::default());
 
     for entity in query.iter() {
@@ -339,87 +1244,196 @@ fn reset(
 
 // 定义竞争鸟的移动，不受玩家控制，也不与障碍物碰撞
 // 其功能只用来和玩家控制的角色比较速度
-fn rival_movement(mut query: Query<&mut Transform, With<Rival>>, time: Res<Time>) {
-    let speed = 5.; // 固定速度
+// 竞争鸟在屏幕上的位置表示它领先玩家的距离
+fn rival_movement(
+    mut query: Query<(&mut Transform, &mut Rival)>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    race: Res<RivalRace>,
+    dir: Res<ScrollDir>,
+) {
+    let speed = 5.; // 靠近目标位置的速度
+    let dt = time_scale.delta_seconds(&time);
+
+    // 领先时在鸟的前方，落后时退到鸟的后方直至飞出屏幕
+    let target_x = (3. + race.lead * 0.5).clamp(-12., 12.);
 
     // 让角色在 x 和 y 坐标方向进行平移变换（translation）
     // 表现出来的效果就是该角色往前上上下下往复运动
-    for mut transform in query.iter_mut() {
-        if transform.translation.x < 3. {
-            transform.translation.x += speed * time.delta_seconds();
-        }
-
-        let floaty = (time.seconds_since_startup() as f32).sin();
-        transform.translation.y = 4. + floaty;
+    for (mut transform, mut rival) in query.iter_mut() {
+        let dx = target_x + rival.offset.x - transform.translation.x * dir.0;
+        transform.translation.x += dx.clamp(-speed, speed) * dt * dir.0;
+
+        // 每只鸟上下浮动的相位不同，鸟群看起来不会整齐划一
+        // 相位按帧间隔累加，重新开始一局时从生成时的相位继续，不会跳动
+        rival.phase += dt;
+        let t = rival.phase;
+        let floaty = t.sin();
+        transform.translation.y = 4. + rival.offset.y + floaty;
         // 还有一次旋转
         // Quat 是表示四元数，可以搜索「渲染 四元数 旋转」
-        transform.rotation = Quat::from_rotation_z((time.seconds_since_startup() as f32).cos() / 4.)
+        transform.rotation = dir.rotation(Quat::from_rotation_z(t.cos() / 4.))
     }
 }
 
-// 生成 竞争鸟 实体并插入组件数据
-fn spawn_rival(mut commands: Commands, gltf_assets: Res<GltfAssets>) {
-    commands
-        .spawn_bundle(SceneBundle { // Bundle 可以看作一种模版，通过它可以很容易创建一组使用通用组件的实体
-            scene: gltf_assets.birb_gold.clone(),
-            transform: Transform::from_xyz(-10., 4., 2.5).with_scale(Vec3::splat(0.25)), // 对模型进行大小缩放
-            ..default()
-        })
-        .insert(CurrentRotationZ(0.))
-        .insert(Rival);
+// 竞争鸟以固定速度前进，与玩家的有效距离比较
+// 第一次超过竞争鸟时发送 Action::BeatRival，每局只发送一次，reset 时重置
+fn rival_race(
+    mut race: ResMut<RivalRace>,
+    mut actions: EventWriter<Action>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
+    traveled: Res<Distance>,
+    score: Res<Score>,
+) {
+    race.rival_distance += RIVAL_SPEED * time_scale.delta_seconds(&time);
+    race.player_distance = traveled.0 + score.0 as f32 * RIVAL_SCORE_DISTANCE;
+    race.lead = race.rival_distance - race.player_distance;
+
+    if race.beaten() && !race.overtaken {
+        race.overtaken = true;
+        actions.send(Action::BeatRival);
+    }
 }
 
-// 当发生BadFlap事件时播放对应音乐
-fn bad_flap_sound(
-    audio_assets: Res<AudioAssets>,
-    audio: Res<Audio>,
+// 分数第一次达到里程碑时发送 Action::Milestone，一帧内跨过几个里程碑时依次发送
+fn score_milestones(
+    mut milestones: ResMut<Milestones>,
+    mut actions: EventWriter<Action>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+) {
+    // 禅模式不显示分数
+    if mode.is_zen() {
+        return;
+    }
+
+    while let Some(&milestone) = SCORE_MILESTONES.get(milestones.0) {
+        if score.0 < milestone {
+            break;
+        }
+        milestones.0 += 1;
+        actions.send(Action::Milestone(milestone));
+    }
+}
+
+// 生成 竞争鸟 实体并插入组件数据
+// 鸟群中的每只鸟错开位置：上下交替排开，越往后离得越远
+fn spawn_rival(
+    mut commands: Commands,
+    gltf_assets: Res<GltfAssets>,
+    count: Res<RivalCount>,
+    scale: Res<BirbScale>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    dir: Res<ScrollDir>,
+) {
+    // 禅模式下没有竞争鸟
+    if mode.is_zen() {
+        return;
+    }
+
+    for i in 0..count.0 {
+        let row = i.div_ceil(2) as f32;
+        let side = if i % 2 == 0 { 1. } else { -1. };
+        let offset = Vec3::new(-0.8 * row, 0.7 * row * side, 0.6 * row);
+        let phase = i as f32 * 0.9;
+        // 生成时就处于浮动的起始相位，第一帧不会跳动
+        let start = Vec3::new(-10., 4. + phase.sin(), 2.5) + offset;
+
+        // Bundle 可以看作一种模版，通过它可以很容易创建一组使用通用组件的实体
+        commands
+            .spawn_bundle(SceneBundle {
+                scene: settings.skin.rival().scene(&gltf_assets),
+                transform: dir.transform(
+                    Transform::from_translation(start)
+                        .with_scale(Vec3::splat(scale.0)) // 对模型进行大小缩放
+                        .with_rotation(Quat::from_rotation_z(phase.cos() / 4.)),
+                ),
+                ..default()
+            })
+            .insert(CurrentRotationZ(0.))
+            .insert(Rival { offset, phase })
+            .insert(RivalSkin);
+    }
+}
+
+// 按键音的音量，相对于音效音量，比拍打翅膀的声音轻很多
+const KEY_HIT_VOLUME: f32 = 0.25;
+// 旋律音符的音量，相对于音效音量
+const MELODY_VOLUME: f32 = 0.4;
+
+// 输对字母时播放轻微的按键音，开启旋律时改为按字母音高播放拍打翅膀的声音
+fn key_hit_sound(
+    audio_assets: Res<AudioAssets>,
+    mut sfx: EventWriter<PlaySfx>,
+    melodic: Res<MelodicKeys>,
+    mut game_rng: ResMut<GameRng>,
     mut events: EventReader<Action>,
 ) {
     for e in events.iter() {
-        if let Action::BadFlap = e {
-            audio.play(audio_assets.badflap.clone());
+        if let Action::KeyHit { char } = e {
+            if melodic.0 {
+                play_sfx_scaled(
+                    &mut sfx,
+                    audio_assets.flap.clone(),
+                    MELODY_VOLUME,
+                    note_speed(*char),
+                );
+                continue;
+            }
+
+            play_sfx_quiet(
+                &mut sfx,
+                audio_assets.key.clone(),
+                KEY_HIT_VOLUME,
+                game_rng.stream(RngStream::Pitch),
+            );
+        }
+    }
+}
+
+// 当发生BadFlap事件时播放对应音乐
+fn bad_flap_sound(
+    audio_assets: Res<AudioAssets>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut events: EventReader<Action>,
+) {
+    for e in events.iter() {
+        if let Action::BadFlap | Action::WordExpired = e {
+            play_sfx(&mut sfx, audio_assets.badflap.clone());
         }
     }
 }
 
 // 游戏音乐
 fn game_music(
-    mut commands: Commands,
     audio_assets: Res<AudioAssets>,
     audio_sinks: Res<Assets<AudioSink>>,
     audio: Res<Audio>,
-    controller: Option<Res<MusicController>>,
+    mut controller: ResMut<MusicController>,
 ) {
-    if let Some(controller) = controller {
-        if let Some(sink) = audio_sinks.get(&controller.0) {
-            sink.pause();
-        }
-    }
-    let handle = audio_sinks
-        .get_handle(audio.play_with_settings(audio_assets.game.clone(), PlaybackSettings::LOOP));
-    commands.insert_resource(MusicController(handle));
+    controller.play(&audio, &audio_sinks, audio_assets.game.clone());
 }
 
 // 开屏音乐
 fn start_screen_music(
-    mut commands: Commands,
     audio_assets: Res<AudioAssets>,
     audio_sinks: Res<Assets<AudioSink>>,
     audio: Res<Audio>,
-    controller: Option<Res<MusicController>>,
+    mut controller: ResMut<MusicController>,
 ) {
-    if let Some(controller) = controller {
-        if let Some(sink) = audio_sinks.get(&controller.0) {
-            sink.pause();
-        }
-    }
-    let handle = audio_sinks
-        .get_handle(audio.play_with_settings(audio_assets.menu.clone(), PlaybackSettings::LOOP));
-    commands.insert_resource(MusicController(handle));
+    controller.play(&audio, &audio_sinks, audio_assets.menu.clone());
 }
 
 // 生成玩家控制的角色
-fn spawn_birb(mut commands: Commands, gltf_assets: Res<GltfAssets>) {
+fn spawn_birb(
+    mut commands: Commands,
+    gltf_assets: Res<GltfAssets>,
+    hitbox: Res<BirbHitbox>,
+    scale: Res<BirbScale>,
+    settings: Res<Settings>,
+    dir: Res<ScrollDir>,
+) {
     // 位置的三维向量
     let pos = Vec3::new(0., BIRB_START_Y, 0.);
 
@@ -437,16 +1451,16 @@ fn spawn_birb(mut commands: Commands, gltf_assets: Res<GltfAssets>) {
     // 对于需要检测的物体 A和物体 B 我们需要将其用 A盒（box）和 B盒将其包装起来
     // 然后判断A盒和B盒在 x轴向和 y轴向是否发生碰撞，只有在 x 轴向和 y轴向都发生碰撞我们才判断它发生了碰撞。
     // 具体碰撞检测算法见 util.rs
-    let aabb = Aabb {
-        center: Vec3A::splat(0.),
-        half_extents: Vec3A::new(0.2, 0.3, 0.25),
-    };
+    // 碰撞盒大小由 BirbHitbox 和 BirbScale 决定
+    let aabb = hitbox.aabb(&scale);
 
     // 创建 bird 实体
     commands
         .spawn_bundle(SceneBundle {
-            scene: gltf_assets.birb.clone(),
-            transform: Transform::from_translation(pos).with_scale(Vec3::splat(0.25)),
+            scene: settings.skin.scene(&gltf_assets),
+            transform: Transform::from_translation(pos)
+                .with_scale(Vec3::splat(scale.0))
+                .with_rotation(dir.rotation(Quat::IDENTITY)),
             ..default()
         })
         // 插入玩家每次控制的目标位置组件
@@ -455,227 +1469,656 @@ fn spawn_birb(mut commands: Commands, gltf_assets: Res<GltfAssets>) {
         .insert(CurrentRotationZ(0.))
         // 插入aabb碰撞检测组件
         .insert(aabb)
+        .insert(PreviousPosition(pos))
         // 插入 bird 组件
         .insert(Birb);
 }
 
+// 按 F3 切换宽松/严格碰撞盒，不影响打字
+fn toggle_birb_hitbox(keyboard: Res<Input<KeyCode>>, mut hitbox: ResMut<BirbHitbox>) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        *hitbox = hitbox.toggle();
+        info!("Birb hitbox: {:?}", *hitbox);
+    }
+}
+
+// BirbHitbox 改变后更新鸟的 Aabb 组件
+// collision 每一帧都重新读取 Aabb 组件，修改后下一次碰撞检测立即生效
+fn apply_birb_hitbox(
+    hitbox: Res<BirbHitbox>,
+    scale: Res<BirbScale>,
+    mut query: Query<&mut Aabb, With<Birb>>,
+) {
+    if !hitbox.is_changed() {
+        return;
+    }
+
+    for mut aabb in query.iter_mut() {
+        *aabb = hitbox.aabb(&scale);
+    }
+}
+
+// 通过 World 插入 Used：命令执行时实体可能已经随障碍物一起被销毁
+fn mark_used(commands: &mut Commands, entity: Entity) {
+    commands.add(move |world: &mut World| {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.insert(Used);
+        }
+    });
+}
+
+// 本帧鸟穿过的空隙，每帧最多只计一次分数
+// 障碍物很密时鸟可能同时碰到两个空隙，另一个空隙在之后的帧里仍未使用，不会漏掉
+fn passed_gap(birb: &Aabb, gaps: impl IntoIterator<Item = (Entity, Aabb)>) -> Option<Entity> {
+    gaps.into_iter()
+        .find(|(_, gap)| collide_aabb(gap, birb))
+        .map(|(entity, _)| entity)
+}
+
 // 碰撞处理
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn collision(
     mut commands: Commands,
-    birb_query: Query<(&Aabb, &Transform), With<Birb>>,
+    mut birb_query: Query<
+        (
+            &Aabb,
+            &Transform,
+            &mut PreviousPosition,
+            &mut TargetPosition,
+        ),
+        With<Birb>,
+    >,
     score_collider_query: Query<
         (&Aabb, &GlobalTransform, Entity),
         (With<ScoreCollider>, Without<Used>),
     >,
-    obstacle_collider_query: Query<(&Aabb, &GlobalTransform), With<ObstacleCollider>>,
+    obstacle_collider_query: Query<
+        (&Aabb, &GlobalTransform, &Parent, Entity),
+        (With<ObstacleCollider>, Without<Used>),
+    >,
     mut score: ResMut<Score>,
+    // bevy 的系统最多只能有 16 个参数，计分规则放在一起
+    (pass_score, reaction, mut hits, mode): (
+        Res<PassScore>,
+        Res<HitReaction>,
+        ResMut<Hits>,
+        Res<GameMode>,
+    ),
     mut state: ResMut<State<AppState>>,
     audio_assets: Res<AudioAssets>,
-    audio: Res<Audio>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+    (speed, dir): (Res<Speed>, Res<ScrollDir>),
+    mut shake: ResMut<CameraShake>,
+    mut bursts: EventWriter<ParticleBurst>,
+    (mut grace, mut time_scale): (ResMut<Grace>, ResMut<TimeScale>),
 ) {
-    let (birb, transform) = birb_query.single();
-    let mut birb = birb.clone();
-    birb.center += Vec3A::from(transform.translation);
+    grace.0.tick(time.delta());
 
-    // 累计经过障碍物且未碰撞次数的分数
-    for (score_aabb, transform, entity) in score_collider_query.iter() {
-        let mut score_aabb = score_aabb.clone();
-        score_aabb.center += Vec3A::from(transform.translation());
+    // 每一帧都读取鸟当前的 Aabb 组件，不做缓存，BirbHitbox 的修改可以立即生效
+    let (birb_aabb, transform, mut previous, mut target) = birb_query.single_mut();
+    let mut birb = birb_aabb.clone();
+    birb.center += Vec3A::from(transform.translation);
 
-        // 
-        if collide_aabb(&score_aabb, &birb) {
-            commands.entity(entity).insert(Used);
-            score.0 += 2;
+    // 上一帧鸟的位置，换算到障碍物当前的参考系
+    // 障碍物这一帧向后移动了 delta，相对障碍物而言鸟是向前移动的
+    let delta = time_scale.delta_seconds(&time) * speed.current;
+    let mut birb_prev = birb_aabb.clone();
+    birb_prev.center += Vec3A::from(previous.0 - Vec3::X * delta * dir.0);
+    previous.0 = transform.translation;
 
-            audio.play(audio_assets.score.clone());
-        }
+    // 累计经过障碍物且未碰撞次数的分数
+    let gaps = score_collider_query
+        .iter()
+        .map(|(score_aabb, transform, entity)| {
+            let mut score_aabb = score_aabb.clone();
+            score_aabb.center += Vec3A::from(transform.translation());
+            (entity, score_aabb)
+        });
+    if let Some(entity) = passed_gap(&birb, gaps) {
+        mark_used(&mut commands, entity);
+        score.0 += pass_score.0;
+
+        play_sfx_varied(
+            &mut sfx,
+            audio_assets.score.clone(),
+            game_rng.stream(RngStream::Pitch),
+        );
+        bursts.send(ParticleBurst {
+            position: Vec3::from(birb.center),
+            kind: BurstKind::Score,
+        });
     }
+    // 向外扩大的碰撞盒，用于判断是否擦边飞过
+    let mut near_miss = birb.clone();
+    near_miss.half_extents += Vec3A::splat(NEAR_MISS_MARGIN);
+    let mut grazed = false;
+
     // 处理与障碍物碰撞时的状况
-    for (obstacle_aabb, transform) in obstacle_collider_query.iter() {
+    for (obstacle_aabb, transform, parent, _) in obstacle_collider_query.iter() {
         let mut obstacle_aabb = obstacle_aabb.clone();
         obstacle_aabb.center += Vec3A::from(transform.translation());
 
-        // 检测到障碍物碰撞时结束屏幕并且播放对应音乐
-        if collide_aabb(&obstacle_aabb, &birb) {
-            state.set(AppState::EndScreen).unwrap();
+        if !swept_collide_aabb(&birb, &birb_prev, &obstacle_aabb) {
+            grazed |= collide_aabb(&near_miss, &obstacle_aabb);
+            continue;
+        }
 
-            audio.play(audio_assets.crash.clone());
+        // 无敌时间内忽略轻微的碰撞
+        // 本帧穿过了障碍物（当前位置没有重合）或嵌入太深时仍然算作碰撞
+        let shallow = aabb_overlap(&birb, &obstacle_aabb)
+            .is_some_and(|depth| depth.min_element() < GRACE_MAX_DEPTH);
+        if grace.active() && shallow {
+            continue;
+        }
 
-            // it's possible to collide with the pipe and flange simultaneously
-            // so we should only react to one game-ending collision.
-            break;
+        play_sfx(&mut sfx, audio_assets.crash.clone());
+        shake.start();
+        bursts.send(ParticleBurst {
+            position: Vec3::from(birb.center),
+            kind: BurstKind::Crash,
+        });
+
+        // 没有结束游戏时扣分并把鸟弹离障碍物，整个障碍物标记为已使用，之后直接穿过
+        hits.0 += 1;
+        // 禅模式下撞到也不会结束
+        if mode.is_zen() || !reaction.is_fatal(hits.0) {
+            score.0 = score.0.saturating_sub(HIT_PENALTY);
+
+            let away = (birb.center.y - obstacle_aabb.center.y).signum();
+            target.0.y = (birb.center.y + away * HIT_KNOCKBACK).clamp(BIRB_MIN_Y, BIRB_MAX_Y);
+
+            for (_, _, other, entity) in obstacle_collider_query.iter() {
+                if other.get() == parent.get() {
+                    mark_used(&mut commands, entity);
+                }
+            }
+            return;
         }
+
+        // 检测到障碍物碰撞时结束屏幕，结束屏幕不需要慢动作
+        *time_scale = TimeScale::default();
+        state.set(AppState::EndScreen).unwrap();
+
+        // it's possible to collide with the pipe and flange simultaneously
+        // so we should only react to one game-ending collision.
+        return;
     }
+
+    // 擦边飞过时开始无敌时间和慢动作，无敌时间内不会重新计时，避免一直贴着障碍物飞
+    if grazed && !grace.active() {
+        grace.0.reset();
+        time_scale.slow_down();
+    }
+}
+
+fn slow_motion(mut time_scale: ResMut<TimeScale>, time: Res<Time>) {
+    time_scale.tick(time.delta());
+}
+
+// 上下移动的障碍物
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+struct MovingObstacle {
+    amplitude: f32, // 振幅
+    speed: f32,     // 角速度
+    phase: f32,     // 当前相位
 }
 
-// 生成障碍物
+// 障碍物对象池中闲置的障碍物
+#[derive(Component)]
+struct Parked;
+
+// 障碍物的各个部分，回收障碍物时据此重新设置位置和网格
+#[derive(Component, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ObstaclePart {
+    BottomPipe,
+    BottomFlange,
+    TopPipe,
+    TopFlange,
+    Gap,
+}
+
+// 上下障碍物的外形
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum PipeKind {
+    Cylinder,
+    Cone,
+    Capsule,
+}
+impl PipeKind {
+    fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..10) {
+            0 | 1 => Self::Cone,
+            2 | 3 => Self::Capsule,
+            _ => Self::Cylinder,
+        }
+    }
+
+    // 以原点为中心的网格，碰撞用的 Aabb 由 compute_aabb 计算
+    fn mesh(self, radius: f32, height: f32) -> Mesh {
+        match self {
+            Self::Cylinder => cylinder::Cylinder {
+                radius,
+                resolution: 16,
+                segments: 1,
+                height,
+            }
+            .into(),
+            Self::Cone => shapes::Cone {
+                radius,
+                resolution: 16,
+                height,
+            }
+            .into(),
+            Self::Capsule => shapes::Capsule {
+                radius,
+                resolution: 16,
+                latitudes: 4,
+                height,
+            }
+            .into(),
+        }
+    }
+}
+
+// 障碍物的布局，由 spawn_obstacle 按空隙序列生成，保存在障碍物上
+// 快照恢复时据此重新摆放屏幕上的障碍物，不再消耗随机数
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct ObstacleLayout {
+    gap_start: f32, // 空隙底部的高度
+    gap_size: f32,
+    radius: f32, // 圆柱体半径
+    kind: PipeKind,
+    amplitude: f32, // 上下移动的振幅，不移动时为 0
+}
+
+// 障碍物各个部分的查询，spawn_obstacle 和快照恢复共用
+type ObstaclePartQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ObstaclePart,
+        &'static mut Transform,
+        Option<&'static Handle<Mesh>>,
+        Option<&'static mut Handle<StandardMaterial>>,
+    ),
+    Without<Obstacle>,
+>;
+
+// 上下圆柱体中间的计分区域，以空隙底部为原点，宽度随圆柱体半径缩放
+fn gap_aabb(gap_size: f32, radius: f32) -> Aabb {
+    let scale = radius / PIPE_RADIUS;
+    Aabb::from_min_max(
+        Vec3::new(-0.1 * scale, 0., -0.5 * scale),
+        Vec3::new(1.0 * scale, gap_size, 0.5 * scale),
+    )
+}
+
+// 缩放后的圆柱体盖子的碰撞盒
+// 盖子共用同一个网格，通过 Transform 缩放，碰撞检测不考虑缩放，所以要单独计算
+fn flange_aabb(scale: f32) -> Aabb {
+    let half_extents = Vec3::new(
+        FLANGE_RADIUS * scale,
+        FLANGE_HEIGHT / 2.,
+        FLANGE_RADIUS * scale,
+    );
+    Aabb::from_min_max(-half_extents, half_extents)
+}
+
+// 分数变化时按 SpacingRamp 缩小障碍物之间的距离
+// 起始距离由难度决定，reset 和 apply_difficulty 会在新的一局开始时恢复
+fn tighten_spacing(
+    score: Res<Score>,
+    run_difficulty: Res<RunDifficulty>,
+    ramp: Res<SpacingRamp>,
+    mut spacing: ResMut<ObstacleSpacing>,
+) {
+    if !score.is_changed() {
+        return;
+    }
+
+    let next = ramp.spacing(run_difficulty.0.obstacle_spacing(), score.0);
+    if next != spacing.0 {
+        spacing.0 = next;
+    }
+}
+
+// 对象池大小：可见范围内最多同时存在的障碍物数量
+fn obstacle_pool_size(spacing: f32) -> usize {
+    ((OBSTACLE_SPAWN_X - OBSTACLE_DESPAWN_X) / spacing).ceil() as usize + 1
+}
+
+// 进入游戏时预先生成固定数量的闲置障碍物，之后只回收不销毁
+fn fill_obstacle_pool(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    obstacle_meshes: Res<ObstacleMeshes>,
+    obstacle_material: Res<ObstacleMaterial>,
+    spacing: Res<ObstacleSpacing>,
+    ramp: Res<SpacingRamp>,
+    dir: Res<ScrollDir>,
+) {
+    // 按缩小后的最小距离准备足够的障碍物
+    let min_spacing = ramp.spacing(spacing.0, u32::MAX);
+    for _ in 0..obstacle_pool_size(min_spacing) {
+        // 每个障碍物拥有自己的上下圆柱体网格，回收时原地替换
+        let bottom_cylinder = meshes.add(Mesh::from(shape::Cube::default()));
+        let top_cylinder = meshes.add(Mesh::from(shape::Cube::default()));
+
+        // 生成圆柱体实体
+        // Bevy 支持通过 Parent 和 Children 创建逻辑层次结构
+        // 创建四个父圆柱实体，用于生成随着小鸟移动而不断出现的子实体
+        commands
+            .spawn_bundle((
+                Transform::from_xyz(OBSTACLE_SPAWN_X * dir.0, 0., 0.),
+                GlobalTransform::default(),
+                Visibility { is_visible: false }, // 闲置时不可见
+                ComputedVisibility::default(),
+            ))
+            .with_children(|parent| {
+                for (part, mesh) in [
+                    (ObstaclePart::BottomPipe, bottom_cylinder.clone()),
+                    (ObstaclePart::BottomFlange, obstacle_meshes.flange.clone()),
+                    (ObstaclePart::TopPipe, top_cylinder.clone()),
+                    (ObstaclePart::TopFlange, obstacle_meshes.flange.clone()),
+                ] {
+                    parent
+                        .spawn()
+                        // 插入 Pbr 物理渲染 bundle
+                        .insert_bundle(PbrBundle {
+                            mesh,
+                            material: obstacle_material.0.clone(),
+                            ..Default::default()
+                        })
+                        .insert(part)
+                        .insert(ObstacleCollider); // 插入碰撞检测组件
+                }
+
+                // 创建上下圆柱体中间aabb层用于计算未碰撞的分数
+                parent
+                    .spawn()
+                    .insert_bundle((Transform::default(), GlobalTransform::default()))
+                    .insert(gap_aabb(GapSize::default().0, PIPE_RADIUS))
+                    .insert(ObstaclePart::Gap)
+                    .insert(ScoreCollider);
+            })
+            .insert(Obstacle)
+            .insert(Parked);
+    }
+}
+
+// 生成障碍物：从对象池中取出一个闲置的障碍物，按新的空隙重新设置
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn spawn_obstacle(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
     spacing: Res<ObstacleSpacing>,
     mut distance: ResMut<DistanceToSpawn>,
-    mut speed: ResMut<Speed>,
+    traveled: Res<Distance>,
     mut bag: ResMut<NextGapBag>,
+    run_difficulty: Res<RunDifficulty>,
+    variety: Res<ObstacleVariety>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    base_gap: Res<GapSize>,
+    dir: Res<ScrollDir>,
+    (gap_colors, gap_materials): (Res<GapColors>, Res<GapColorMaterials>),
+    mut obstacle_query: Query<
+        (Entity, &mut Transform, &mut Visibility, &Children),
+        (With<Obstacle>, With<Parked>),
+    >,
+    mut part_query: ObstaclePartQuery,
 ) {
     if distance.0 > 0. {
         return;
     }
 
+    let (entity, mut transform, mut visibility, children) = match obstacle_query.iter_mut().next() {
+        Some(obstacle) => obstacle,
+        // 对象池尚未生成（刚进入游戏的第一帧）
+        None => return,
+    };
+
     // 设定初始距离
     distance.0 = spacing.0;
+    if mode.is_zen() {
+        distance.0 *= ZEN_SPACING_SCALE;
+    }
 
-    speed.increase(0.1);
+    let difficulty = run_difficulty.0;
 
     // 空隙
-    let gap_start = bag.next().unwrap();
-
-    // 圆柱体盖子的高度和半径
-    let flange_height = 0.4;
-    let flange_radius = 0.8;
+    let (gap_start, jump) = bag.next_with_kind().unwrap();
+    debug!("Gap at {:.2} after a {:?} jump", gap_start, jump);
+    // 圆柱体半径和空隙大小，空隙随飞行距离逐渐缩小
+    // 禅模式下空隙固定放大，不随距离缩小
+    let gap_scale = if mode.is_zen() {
+        ZEN_GAP_SCALE
+    } else {
+        (1. - traveled.0 * GAP_RAMP_PER_DISTANCE).max(GAP_RAMP_MIN_SCALE)
+    };
+    let shape = bag.next_shape(PIPE_RADIUS, base_gap.0 * gap_scale, MIN_GAP_SIZE);
+    // 未开启多样化时不消耗随机数，保证相同种子得到相同的关卡
+    let kind = if variety.0 {
+        PipeKind::random(bag.rng())
+    } else {
+        PipeKind::Cylinder
+    };
 
-    // 底部障碍物高度
-    let bottom_height = gap_start;
-    // 在网格上增加底部圆柱体
-    let bottom_cylinder = meshes.add(
-        cylinder::Cylinder {
-            radius: 0.75,
-            resolution: 16,
-            segments: 1,
-            height: bottom_height,
+    // 分数较高以后，部分障碍物会上下移动
+    // 振幅受限于空隙的坐标范围，保证空隙始终在鸟可以到达的位置
+    let gap_range = base_gap.start_range();
+    let amplitude = (gap_start - gap_range.start)
+        .min(gap_range.end - gap_start)
+        .min(MOVING_OBSTACLE_MAX_AMPLITUDE);
+    let moving = !mode.is_zen()
+        && score.0 >= MOVING_OBSTACLE_MIN_SCORE
+        && amplitude >= MOVING_OBSTACLE_MIN_AMPLITUDE
+        && bag.rng().gen_bool(difficulty.moving_obstacle_chance());
+    let moving = moving.then(|| {
+        let rng = bag.rng();
+        MovingObstacle {
+            amplitude,
+            speed: rng.gen_range(1.0..2.0),
+            phase: rng.gen_range(0.0..std::f32::consts::TAU),
         }
-        .into(), // 将 Cylinder 转为 Mesh (网格)
+    });
+
+    let layout = ObstacleLayout {
+        gap_start,
+        gap_size: shape.gap_size,
+        radius: shape.radius,
+        kind,
+        amplitude: moving.map_or(0., |moving| moving.amplitude),
+    };
+    // 开启空隙颜色时按本次空隙的松紧换材质，只在预先创建的几种材质之间切换
+    let color = gap_colors
+        .0
+        .then(|| &gap_materials.0[gap_color_step(layout.gap_size, base_gap.0)]);
+
+    place_obstacle(
+        &mut commands,
+        &mut meshes,
+        &mut part_query,
+        children,
+        &layout,
+        color,
     );
-    let bottom_y = bottom_height / 2.;
+
+    transform.translation.x = OBSTACLE_SPAWN_X * dir.0;
+    transform.translation.y = 0.;
+    visibility.is_visible = true;
+    let mut obstacle = commands.entity(entity);
+    obstacle.remove::<Parked>().insert(layout);
+    match moving {
+        Some(moving) => obstacle.insert(moving),
+        None => obstacle.remove::<MovingObstacle>(),
+    };
+}
+
+// 按布局设置障碍物各个部分的网格、位置和碰撞盒
+// 回收的计分区域需要重新计分，练习模式下撞过的部分需要重新参与碰撞，所以清除 Used
+fn place_obstacle(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    part_query: &mut ObstaclePartQuery,
+    children: &Children,
+    layout: &ObstacleLayout,
+    color: Option<&Handle<StandardMaterial>>,
+) {
+    let ObstacleLayout {
+        gap_start,
+        gap_size,
+        radius,
+        kind,
+        amplitude,
+    } = *layout;
+    let flange_scale = radius / PIPE_RADIUS;
+
+    // 底部障碍物高度
+    // 移动的障碍物向下延长，上移时底部不会离开地面
+    let bottom_height = gap_start + amplitude;
+    let bottom_y = (gap_start - amplitude) / 2.;
 
     // 顶部圆柱体相关数据设置
-    let top_height = 10. - gap_start - GAP_SIZE;
-    let top_cylinder = meshes.add(
-        cylinder::Cylinder {
-            radius: 0.75,
-            resolution: 16,
-            segments: 1,
-            height: top_height,
+    let top_height = 10. - gap_start - gap_size;
+    let top_y = gap_start + gap_size + top_height / 2.;
+
+    let bottom_flange_y = gap_start - FLANGE_HEIGHT / 2.;
+    let top_flange_y = gap_start + gap_size + FLANGE_HEIGHT / 2.;
+
+    for child in children.iter() {
+        let (part_entity, part, mut part_transform, mesh, material) =
+            match part_query.get_mut(*child) {
+                Ok(part) => part,
+                Err(_) => continue,
+            };
+
+        if let (Some(color), Some(mut material)) = (color, material) {
+            *material = color.clone();
         }
-        .into(),
-    );
-    let top_y = gap_start + GAP_SIZE + top_height / 2.;
 
-    let flange = meshes.add(
-        cylinder::Cylinder {
-            radius: flange_radius,
-            resolution: 16,
-            segments: 1,
-            height: flange_height,
+        // 上下圆柱体高度随空隙变化，原地替换网格并更新碰撞用的 Aabb
+        let height = match part {
+            ObstaclePart::BottomPipe => Some(bottom_height),
+            ObstaclePart::TopPipe => Some(top_height),
+            _ => None,
+        };
+        if let (Some(height), Some(mesh)) = (height, mesh) {
+            let pipe = kind.mesh(radius, height);
+            commands
+                .entity(part_entity)
+                .insert(pipe.compute_aabb().unwrap());
+            let _ = meshes.set(mesh, pipe);
+
+            // 圆锥体的底面朝向空隙，与盖子相接，底部的圆锥体需要倒过来
+            // 网格关于原点对称，旋转后 Aabb 不变
+            let flip = kind == PipeKind::Cone && *part == ObstaclePart::BottomPipe;
+            part_transform.rotation = if flip {
+                Quat::from_rotation_x(std::f32::consts::PI)
+            } else {
+                Quat::IDENTITY
+            };
         }
-        .into(),
-    );
-    let bottom_flange_y = gap_start - flange_height / 2.;
-    let top_flange_y = gap_start + GAP_SIZE + flange_height / 2.;
-
-    // 上下圆柱体中间空隙
-    let middle: Mesh = shape::Box {
-        min_x: -0.1,
-        max_x: 1.0,
-        min_y: gap_start,
-        max_y: gap_start + GAP_SIZE,
-        min_z: -0.5,
-        max_z: 0.5,
-    }
-    .into();
-
-    // 生成圆柱体实体
-    // Bevy 支持通过 Parent 和 Children 创建逻辑层次结构
-    // 创建四个父圆柱实体，用于生成随着小鸟移动而不断出现的子实体
-    commands
-        .spawn_bundle((
-            Transform::from_xyz(38., 0., 0.),
-            GlobalTransform::default(),
-            Visibility::default(), // 可见性
-            ComputedVisibility::default(),
-        ))
-        .with_children(|parent| {
-            // 创建底部圆柱体
-            parent
-                .spawn()
-                // 插入 Pbr 物理渲染 bundle
-                .insert_bundle(PbrBundle {
-                    transform: Transform::from_xyz(0., bottom_y, 0.),
-                    mesh: bottom_cylinder,
-                    material: materials.add(Color::GREEN.into()),
-                    ..Default::default()
-                })
-                .insert(ObstacleCollider); // 插入碰撞检测组件
-            // 创建底部圆柱体的盖子
-            parent
-                .spawn()
-                .insert_bundle(PbrBundle {
-                    transform: Transform::from_xyz(0., bottom_flange_y, 0.),
-                    mesh: flange.clone(),
-                    material: materials.add(Color::GREEN.into()),
-                    ..Default::default()
-                })
-                .insert(ObstacleCollider);
-
-            // 创建顶部圆柱体
-            parent
-                .spawn()
-                .insert_bundle(PbrBundle {
-                    transform: Transform::from_xyz(0., top_y, 0.),
-                    mesh: top_cylinder,
-                    material: materials.add(Color::GREEN.into()),
-                    ..Default::default()
-                })
-                .insert(ObstacleCollider);
-            // 创建底部圆柱体的盖子
-            parent
-                .spawn()
-                .insert_bundle(PbrBundle {
-                    transform: Transform::from_xyz(0., top_flange_y, 0.),
-                    mesh: flange.clone(),
-                    material: materials.add(Color::GREEN.into()),
-                    ..Default::default()
-                })
-                .insert(ObstacleCollider);
-
-            // 创建上下圆柱体中间aabb层用于计算未碰撞的分数
-            parent
-                .spawn()
-                .insert_bundle((Transform::default(), GlobalTransform::default()))
-                .insert(middle.compute_aabb().unwrap())
-                .insert(ScoreCollider);
-        })
-        .insert(Obstacle);
+
+        part_transform.translation.y = match part {
+            ObstaclePart::BottomPipe => bottom_y,
+            ObstaclePart::BottomFlange => bottom_flange_y,
+            ObstaclePart::TopPipe => top_y,
+            ObstaclePart::TopFlange => top_flange_y,
+            ObstaclePart::Gap => gap_start,
+        };
+
+        // 盖子随圆柱体半径缩放
+        if matches!(part, ObstaclePart::BottomFlange | ObstaclePart::TopFlange) {
+            part_transform.scale = Vec3::new(flange_scale, 1., flange_scale);
+            commands
+                .entity(part_entity)
+                .insert(flange_aabb(flange_scale));
+        }
+
+        commands.entity(part_entity).remove::<Used>();
+
+        // 计分区域的大小随空隙和半径变化
+        if *part == ObstaclePart::Gap {
+            commands
+                .entity(part_entity)
+                .insert(gap_aabb(gap_size, radius));
+        }
+    }
+}
+
+// 上下移动障碍物
+// 移动的是整个障碍物，碰撞检测读取子实体的 GlobalTransform，碰撞盒会跟着一起移动
+fn moving_obstacle_movement(
+    mut query: Query<(&mut Transform, &mut MovingObstacle), Without<Parked>>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    for (mut transform, mut moving) in query.iter_mut() {
+        moving.phase += time_scale.delta_seconds(&time) * moving.speed;
+        transform.translation.y = moving.phase.sin() * moving.amplitude;
+    }
 }
 
 // 移动障碍物，制造小鸟向前飞的效果
+#[allow(clippy::type_complexity)]
 fn obstacle_movement(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform), With<Obstacle>>,
-    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut Visibility), (With<Obstacle>, Without<Parked>)>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
     mut distance: ResMut<DistanceToSpawn>,
+    mut traveled: ResMut<Distance>,
     speed: Res<Speed>,
+    dir: Res<ScrollDir>,
 ) {
-    let delta = time.delta_seconds() * speed.current;
+    let delta = time_scale.delta_seconds(&time) * speed.current;
 
     distance.0 -= delta;
+    traveled.0 += delta;
 
-    for (entity, mut transform) in query.iter_mut() {
+    for (entity, mut transform, mut visibility) in query.iter_mut() {
         // 向后平移造成小鸟向前移动错觉
-        transform.translation.x -= delta;
-        if transform.translation.x < -30. {
-            commands.entity(entity).despawn_recursive();
+        transform.translation.x -= delta * dir.0;
+        // 移出屏幕后放回对象池
+        if transform.translation.x * dir.0 < OBSTACLE_DESPAWN_X {
+            transform.translation.x = OBSTACLE_SPAWN_X * dir.0;
+            visibility.is_visible = false;
+            commands.entity(entity).insert(Parked);
         }
     }
 }
 
-fn start_screen_movement(mut query: Query<(&mut Transform, &mut TargetPosition)>, time: Res<Time>) {
+fn accelerate(mut speed: ResMut<Speed>, time: Res<Time>, time_scale: Res<TimeScale>) {
+    if speed.current < speed.max {
+        speed.accelerate(time_scale.delta_seconds(&time));
+    }
+}
+
+// 开始屏幕上鸟上下浮动的时间，每次进入开始屏幕时从 0 开始
+// 打开设置等覆盖状态时不计时，返回后接着浮动
+#[derive(Default)]
+struct FloatTime(f32);
+
+fn reset_float_time(mut float_time: ResMut<FloatTime>) {
+    float_time.0 = 0.;
+}
+
+fn start_screen_movement(
+    mut query: Query<(&mut Transform, &mut TargetPosition)>,
+    time: Res<Time>,
+    mut float_time: ResMut<FloatTime>,
+) {
     let speed = 1.0;
     let magnitude = 0.15;
 
+    float_time.0 += time.delta_seconds();
+
     for (mut transform, mut target) in query.iter_mut() {
-        let floaty = (time.seconds_since_startup() as f32 * speed).sin() * magnitude;
-        transform.translation.y = 3. + floaty;
+        let floaty = (float_time.0 * speed).sin() * magnitude;
+        transform.translation.y = BIRB_START_Y + floaty;
         target.0 = transform.translation;
     }
 }
@@ -684,55 +2127,55 @@ fn start_screen_movement(mut query: Query<(&mut Transform, &mut TargetPosition)>
 fn movement(
     mut query: Query<(&mut Transform, &mut CurrentRotationZ, &TargetPosition)>,
     time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    dir: Res<ScrollDir>,
 ) {
     // 固定的速度
     let speed = 2.;
     let rot_speed = 2.;
     let rot_speed_glide = 1.;
 
+    let dt = time_scale.delta_seconds(&time);
+
     // 计算每次移动的目标位置等信息，详细不表
+    // 一帧分成两段：飞向目标的时间和到达以后恢复水平的时间
+    // 这样到达目标的那一帧不会多转一整帧，帧率不同时结果也相同
     for (mut transform, mut rotation, target) in query.iter_mut() {
         let dist = target.0.distance(transform.translation);
 
-        // if we are not moving, seek a neutral rotation
-        if dist <= std::f32::EPSILON {
-            if rotation.0.abs() <= std::f32::EPSILON {
-                continue;
-            }
+        if dist <= f32::EPSILON && rotation.0.abs() <= f32::EPSILON {
+            continue;
+        }
 
-            let delta = time.delta_seconds() * rot_speed_glide;
+        let move_time = (dist / speed).min(dt);
 
-            if rotation.0 < 0. {
-                rotation.0 = (rotation.0 + delta).min(0.);
+        // rotate with the direction of movement, then seek the target position
+        if dist > f32::EPSILON {
+            let dir = target.0 - transform.translation;
+
+            let rot = if dir.y > 0. {
+                move_time * rot_speed
             } else {
-                rotation.0 = (rotation.0 - delta).max(0.);
+                move_time * -rot_speed
             };
+            rotation.0 = (rotation.0 + rot).clamp(-0.5, 0.5);
 
-            transform.rotation = Quat::from_rotation_z(rotation.0);
-
-            continue;
+            if move_time < dt {
+                transform.translation = target.0;
+            } else {
+                transform.translation += dir.normalize() * dt * speed;
+            }
         }
 
-        // otherwise, rotate with the direction of movement
-
-        let dir = target.0 - transform.translation;
-
-        let rot = if dir.y > 0. {
-            time.delta_seconds() * rot_speed
+        // if we are not moving, seek a neutral rotation
+        let delta = (dt - move_time) * rot_speed_glide;
+        if rotation.0 < 0. {
+            rotation.0 = (rotation.0 + delta).min(0.);
         } else {
-            time.delta_seconds() * -rot_speed
+            rotation.0 = (rotation.0 - delta).max(0.);
         };
-        rotation.0 = (rotation.0 + rot).clamp(-0.5, 0.5);
-        transform.rotation = Quat::from_rotation_z(rotation.0);
-
-        // seek the target position
 
-        let delta = dir.normalize() * time.delta_seconds() * speed;
-        if dist < delta.length() {
-            transform.translation = target.0;
-        } else {
-            transform.translation += delta;
-        }
+        transform.rotation = dir.rotation(Quat::from_rotation_z(rotation.0));
     }
 }
 
@@ -741,25 +2184,172 @@ fn retry_game(mut events: EventReader<Action>, mut state: ResMut<State<AppState>
     for e in events.iter() {
         if let Action::Retry = e {
             // 设置游戏App状态为 AppState::StartScreen
+            // 快捷键和输入 "retry" 可能在同一帧发出两次，只处理第一次
             state.set(AppState::StartScreen).unwrap();
+            break;
         }
     }
 }
 
+// 进入结束屏幕后，重试快捷键要等这段时间才生效
+// 避免撞上障碍物时正在打字的 Enter 或 R 直接重新开始
+const RETRY_HOTKEY_DELAY: f32 = 0.5;
+
+struct RetryHotkeyDelay(Timer);
+impl Default for RetryHotkeyDelay {
+    fn default() -> Self {
+        Self(Timer::from_seconds(RETRY_HOTKEY_DELAY, false))
+    }
+}
+
+fn arm_retry_hotkey(mut delay: ResMut<RetryHotkeyDelay>) {
+    delay.0.reset();
+}
+
+// 结束屏幕上按 Enter 或 R 立即重试，与输入 "retry" 效果相同
+// 输入排行榜名字缩写时不响应
+fn retry_hotkey(
+    keyboard: Res<Input<KeyCode>>,
+    capture: Res<TextCapture>,
+    time: Res<Time>,
+    mut delay: ResMut<RetryHotkeyDelay>,
+    mut events: EventWriter<Action>,
+) {
+    delay.0.tick(time.delta());
+    if !delay.0.finished() || capture.active {
+        return;
+    }
+
+    if keyboard.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter, KeyCode::R]) {
+        events.send(Action::Retry);
+    }
+}
+
 // 开始游戏
-fn start_game(mut events: EventReader<Action>, mut state: ResMut<State<AppState>>) {
+fn start_game(
+    mut events: EventReader<Action>,
+    mut state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+) {
     for e in events.iter() {
-        if let Action::Start = e {
-            state.set(AppState::Playing).unwrap();
+        match e {
+            Action::Start => {}
+            Action::StartZen => *mode = GameMode::Zen,
+            _ => continue,
+        }
+        state.set(AppState::Playing).unwrap();
+    }
+}
+
+// 切换鸟的模型
+fn change_skin(mut events: EventReader<Action>, mut settings: ResMut<Settings>) {
+    for e in events.iter() {
+        if let Action::NextSkin = e {
+            settings.skin = settings.skin.next();
+        }
+    }
+}
+
+fn apply_birb_skin(
+    settings: Res<Settings>,
+    gltf_assets: Option<Res<GltfAssets>>,
+    mut birb_query: Query<&mut Handle<Scene>, (With<Birb>, Without<RivalSkin>)>,
+    mut rival_query: Query<&mut Handle<Scene>, (With<RivalSkin>, Without<Birb>)>,
+) {
+    let gltf_assets = match gltf_assets {
+        Some(assets) if settings.is_changed() => assets,
+        _ => return,
+    };
+
+    let birb = settings.skin.scene(&gltf_assets);
+    let rival = settings.skin.rival().scene(&gltf_assets);
+    let handles = birb_query
+        .iter_mut()
+        .map(|handle| (handle, &birb))
+        .chain(rival_query.iter_mut().map(|handle| (handle, &rival)));
+    for (mut handle, scene) in handles {
+        // 只在模型改变时替换，替换会重新生成整个场景
+        if *handle != *scene {
+            *handle = scene.clone();
+        }
+    }
+}
+
+// 切换练习模式，关闭时回到启动时选择的撞击反应
+fn toggle_practice(mut events: EventReader<Action>, mut reaction: ResMut<HitReaction>) {
+    for e in events.iter() {
+        if let Action::TogglePractice = e {
+            *reaction = match *reaction {
+                HitReaction::Practice => match HitReaction::from_env() {
+                    HitReaction::Practice => HitReaction::Die,
+                    other => other,
+                },
+                _ => HitReaction::Practice,
+            };
+        }
+    }
+}
+
+// 在开始屏幕的退出确认中输入 yes 后退出游戏
+// 只在开始屏幕生效，游戏中不会误触发
+fn quit_game(mut events: EventReader<Action>, mut exit: EventWriter<AppExit>) {
+    if events.iter().any(|e| matches!(e, Action::ConfirmQuit)) {
+        exit.send(AppExit);
+    }
+}
+
+// 打开设置，设置界面覆盖在开始屏幕之上，关闭后回到开始屏幕
+fn open_settings(mut events: EventReader<Action>, mut state: ResMut<State<AppState>>) {
+    for e in events.iter() {
+        if let Action::Settings = e {
+            state.push(AppState::Settings).unwrap();
         }
     }
 }
 
 // 更新分数
-fn update_score(mut events: EventReader<Action>, mut score: ResMut<Score>) {
+// 输错时按 MistypePenalty 扣分，最低为 0
+fn update_score(
+    mut events: EventReader<Action>,
+    mut score: ResMut<Score>,
+    penalty: Res<MistypePenalty>,
+) {
     for e in events.iter() {
-        if let Action::IncScore(inc) = e {
-            score.0 += inc
+        let next = score_after(score.0, e, penalty.0);
+        if next != score.0 {
+            score.0 = next;
+        }
+    }
+}
+
+// 单词倒计时结束时扣除的分数
+const WORD_EXPIRED_PENALTY: u32 = 1;
+
+// 一个动作之后的分数，不依赖 Bevy 的系统，可以直接测试
+fn score_after(score: u32, action: &Action, penalty: u32) -> u32 {
+    match action {
+        Action::IncScore(inc) => score + inc,
+        Action::BadFlap => score.saturating_sub(penalty),
+        Action::WordExpired => score.saturating_sub(WORD_EXPIRED_PENALTY),
+        _ => score,
+    }
+}
+
+// 开启下沉后，目标位置持续向下移动，直到最低点
+// 输入单词的上下移动叠加在上面
+fn gravity(
+    mut query: Query<&mut TargetPosition>,
+    gravity: Res<Gravity>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    if gravity.0 <= 0. {
+        return;
+    }
+
+    for mut target in query.iter_mut() {
+        if target.0.y > BIRB_MIN_Y {
+            target.0.y = (target.0.y - gravity.0 * time_scale.delta_seconds(&time)).max(BIRB_MIN_Y);
         }
     }
 }
@@ -769,7 +2359,9 @@ fn update_target_position(
     mut events: EventReader<Action>,
     mut query: Query<&mut TargetPosition>,
     audio_assets: Res<AudioAssets>,
-    audio: Res<Audio>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut game_rng: ResMut<GameRng>,
+    step: Res<FlapStep>,
 ) {
     // 通过事件读取器 EventReader
     // 获取小鸟的状态，然后更新目标位置和播放音乐
@@ -778,24 +2370,32 @@ fn update_target_position(
             // 向上
             Action::BirbUp => {
                 for mut target in query.iter_mut() {
-                    target.0.y += 0.25;
+                    target.0.y += step.0;
                     if target.0.y > BIRB_MAX_Y {
                         target.0.y = BIRB_MAX_Y;
-                        audio.play(audio_assets.bump.clone());
+                        play_sfx(&mut sfx, audio_assets.bump.clone());
                     } else {
-                        audio.play(audio_assets.flap.clone());
+                        play_sfx_varied(
+                            &mut sfx,
+                            audio_assets.flap.clone(),
+                            game_rng.stream(RngStream::Pitch),
+                        );
                     }
                 }
             }
             // 向下
             Action::BirbDown => {
                 for mut target in query.iter_mut() {
-                    target.0.y -= 0.25;
+                    target.0.y -= step.0;
                     if target.0.y < BIRB_MIN_Y {
                         target.0.y = BIRB_MIN_Y;
-                        audio.play(audio_assets.bump.clone());
+                        play_sfx(&mut sfx, audio_assets.bump.clone());
                     } else {
-                        audio.play(audio_assets.flap.clone());
+                        play_sfx_varied(
+                            &mut sfx,
+                            audio_assets.flap.clone(),
+                            game_rng.stream(RngStream::Pitch),
+                        );
                     }
                 }
             }
@@ -806,11 +2406,54 @@ fn update_target_position(
 
 
 // 设置3D摄像机
-fn setup(mut commands: Commands) {
+// 读取设置以后确定鸟的大小、镜像模式和障碍物是否上色
+// 命令行参数和环境变量只在本次运行中有效，不写入设置
+fn apply_settings(
+    settings: Res<Settings>,
+    mut scale: ResMut<BirbScale>,
+    mut dir: ResMut<ScrollDir>,
+    mut gap_colors: ResMut<GapColors>,
+) {
+    *scale = BirbScale::new(&settings);
+    *dir = ScrollDir::new(&settings);
+    *gap_colors = GapColors::new(&settings);
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    texture_assets: Res<TextureAssets>,
+    dir: Res<ScrollDir>,
+) {
+    // 障碍物共用的网格
+    let flange = meshes.add(
+        cylinder::Cylinder {
+            radius: FLANGE_RADIUS,
+            resolution: 16,
+            segments: 1,
+            height: FLANGE_HEIGHT,
+        }
+        .into(),
+    );
+    commands.insert_resource(ObstacleMeshes { flange });
+    commands.insert_resource(ObstacleMaterial(materials.add(StandardMaterial {
+        base_color_texture: Some(texture_assets.pipe.clone()),
+        ..Default::default()
+    })));
+    // 色相从 0（红）到 120（绿）
+    let gap_colors = (0..GAP_COLOR_STEPS)
+        .map(|i| {
+            let hue = 120. * i as f32 / (GAP_COLOR_STEPS - 1) as f32;
+            materials.add(Color::hsl(hue, 0.7, 0.45).into())
+        })
+        .collect();
+    commands.insert_resource(GapColorMaterials(gap_colors));
+
     // camera
     // 创建3D摄像机实体
     commands.spawn_bundle(Camera3dBundle {
-        transform: Transform::from_xyz(4.5, 5.8, 11.7).with_rotation(Quat::from_rotation_x(-0.211)),
+        transform: camera_transform(&dir),
         ..Default::default()
     });
 
@@ -834,12 +2477,491 @@ fn setup(mut commands: Commands) {
             illuminance: 5000., // 光照强度
             ..Default::default()
         },
-        transform: Transform {
-            translation: Vec3::new(0.0, 2.0, 0.0),
-            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_4 / 2.)
-                * Quat::from_rotation_y(std::f32::consts::PI / 8.),
-            ..Default::default()
-        },
+        // 游戏中光源会随昼夜循环变化，见 sky.rs
+        transform: sky::sun_transform(-std::f32::consts::FRAC_PI_4 / 2.),
         ..Default::default()
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::{
+        asset::HandleId,
+        window::{ReceivedCharacter, WindowId},
+    };
+
+    use super::*;
+    use crate::recording::{play_keys, record_keys, Playback, Recorder};
+    use crate::typing::{
+        keyboard, GamepadMode, IgnoreCase, KeyHitOnWholeWords, KeystrokeLogged, Keystrokes,
+        LogKeystrokes, TextCapture, TypingTarget, PERFECT_WORD_BONUS,
+    };
+
+    // 不需要窗口、声音和资源文件的最小 App，只有键盘输入和计分
+    fn typing_app() -> App {
+        let mut app = App::new();
+        app.add_state(AppState::Playing)
+            .add_event::<ReceivedCharacter>()
+            .add_event::<Action>()
+            .init_resource::<Score>()
+            .insert_resource(MistypePenalty(1))
+            .insert_resource(GamepadMode(false))
+            .init_resource::<Keystrokes>()
+            .init_resource::<TextCapture>()
+            .init_resource::<KeyHitOnWholeWords>()
+            .init_resource::<IgnoreCase>()
+            .init_resource::<LogKeystrokes>()
+            .add_event::<KeystrokeLogged>()
+            .add_system(keyboard.label("keyboard"))
+            .add_system(update_score.after("keyboard"));
+        app
+    }
+
+    fn type_str(app: &mut App, input: &str) {
+        let mut events = app.world.resource_mut::<Events<ReceivedCharacter>>();
+        for c in input.chars() {
+            events.send(ReceivedCharacter {
+                id: WindowId::primary(),
+                char: c,
+            });
+        }
+        app.update();
+    }
+
+    fn actions(app: &App) -> Vec<Action> {
+        let events = app.world.resource::<Events<Action>>();
+        events.get_reader().iter(events).cloned().collect()
+    }
+
+    #[test]
+    fn typing_a_word_scores_and_requests_a_new_word() {
+        let mut app = typing_app();
+        let entity = app
+            .world
+            .spawn()
+            .insert(TypingTarget::new(
+                "ab".into(),
+                vec![Action::BirbUp, Action::IncScore(1)],
+            ))
+            .id();
+
+        type_str(&mut app, "ab");
+
+        let actions = actions(&app);
+        let ups = actions
+            .iter()
+            .filter(|a| matches!(a, Action::BirbUp))
+            .count();
+        assert_eq!(ups, 2);
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, Action::NewWord(e) if *e == entity)));
+        assert!(!actions.iter().any(|a| matches!(a, Action::BadFlap)));
+
+        assert_eq!(app.world.resource::<Score>().0, 2 + PERFECT_WORD_BONUS);
+        assert_eq!(app.world.resource::<Keystrokes>().correct, 2);
+    }
+
+    #[test]
+    fn mistype_flaps_badly_and_costs_points() {
+        let mut app = typing_app();
+        let entity = app
+            .world
+            .spawn()
+            .insert(TypingTarget::new("abc".into(), vec![Action::IncScore(1)]))
+            .id();
+
+        type_str(&mut app, "ax");
+
+        assert!(actions(&app).iter().any(|a| matches!(a, Action::BadFlap)));
+        assert_eq!(app.world.resource::<Score>().0, 0);
+        assert_eq!(app.world.resource::<Keystrokes>().wrong, 1);
+
+        let target = app.world.get::<TypingTarget>(entity).unwrap();
+        assert_eq!(target.index, 1);
+        assert_eq!(target.mistakes, 1);
+    }
+
+    #[test]
+    fn quitting_needs_ctrl_held() {
+        let quit_key = QuitKey::default();
+        let mut keyboard = Input::<KeyCode>::default();
+        keyboard.press(KeyCode::Back);
+        keyboard.press(KeyCode::Q);
+        assert!(!quit_key.just_pressed(&keyboard));
+
+        keyboard.clear();
+        keyboard.release_all();
+        keyboard.press(KeyCode::LControl);
+        keyboard.press(KeyCode::Q);
+        assert!(quit_key.just_pressed(&keyboard));
+    }
+
+    #[test]
+    fn flags_read_env_then_args() {
+        let args = |list: &[&str]| {
+            list.iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+
+        // 环境变量优先
+        let scale: Option<f32> = parse_flag(Some(" 0.5 ".into()), args(&["--scale=2"]), "--scale");
+        assert_eq!(scale, Some(0.5));
+        let scale: Option<f32> = parse_flag(None, args(&["--scale=2"]), "--scale");
+        assert_eq!(scale, Some(2.));
+        // 前缀相同的其它参数不算
+        let scale: Option<f32> = parse_flag(None, args(&["--scaled=2"]), "--scale");
+        assert_eq!(scale, None);
+        let scale: Option<f32> = parse_flag(Some("big".into()), args(&[]), "--scale");
+        assert_eq!(scale, None);
+
+        // 只写参数名时用作开关
+        let mirror: Option<String> = parse_flag(None, args(&["--mirror"]), "--mirror");
+        assert_eq!(mirror.as_deref(), Some(""));
+        let mirror: Option<String> = parse_flag(None, args(&["--variety"]), "--mirror");
+        assert_eq!(mirror, None);
+    }
+
+    #[test]
+    fn display_settings_come_from_saved_settings() {
+        let settings = Settings {
+            gap_colors: true,
+            birb_scale: 0.5,
+            mirrored: true,
+            ..Default::default()
+        };
+
+        assert!(GapColors::new(&settings).0);
+        assert_eq!(BirbScale::new(&settings).factor(), 2.);
+        assert_eq!(ScrollDir::new(&settings), ScrollDir(-1.));
+
+        // 无效的缩放比例使用默认值
+        let settings = Settings {
+            birb_scale: 0.,
+            ..Default::default()
+        };
+        assert_eq!(BirbScale::new(&settings).0, BIRB_SCALE);
+    }
+
+    #[test]
+    fn slow_motion_wears_off_in_real_time() {
+        let mut time = Time::default();
+        let now = Instant::now();
+        time.update_with_instant(now);
+        time.update_with_instant(now + Duration::from_millis(100));
+
+        let mut time_scale = TimeScale::default();
+        assert_eq!(time_scale.delta_seconds(&time), time.delta_seconds());
+
+        time_scale.slow_down();
+        time_scale.tick(Duration::from_millis(300));
+        let slowed = time.delta_seconds() * SLOW_MOTION_SPEED;
+        assert!((time_scale.delta_seconds(&time) - slowed).abs() < 1e-6);
+
+        time_scale.tick(Duration::from_millis(300));
+        assert_eq!(time_scale.delta_seconds(&time), time.delta_seconds());
+    }
+
+    #[test]
+    fn speed_ramps_up_to_the_configured_max() {
+        let config = SpeedConfig {
+            start: 3.,
+            max: 4.,
+            ramp: 0.5,
+        };
+        let mut speed = Speed::new(&config);
+        assert_eq!(speed.current, 3.);
+
+        speed.accelerate(1.);
+        assert_eq!(speed.current, 3.5);
+        speed.accelerate(10.);
+        assert_eq!(speed.current, 4.);
+    }
+
+    #[test]
+    fn gap_size_always_fits_below_the_ceiling() {
+        assert_eq!(GapSize::new(0.1).0, MIN_GAP_SIZE);
+
+        let huge = GapSize::new(100.);
+        let range = huge.start_range();
+        assert!(range.start <= range.end);
+        assert!(range.start + huge.0 <= GAP_CEILING_Y);
+
+        for difficulty in Difficulty::ALL {
+            let range = GapSize::new(difficulty.gap_size()).start_range();
+            assert!(!range.is_empty());
+        }
+    }
+
+    #[test]
+    fn tight_gaps_get_the_reddest_color() {
+        let base = GapSize::default().0;
+        assert_eq!(gap_color_step(MIN_GAP_SIZE, base), 0);
+        let wide = base * WIDE_GAP_SCALE;
+        assert_eq!(gap_color_step(wide, base), GAP_COLOR_STEPS - 1);
+        let narrow = base * luck::NARROW_GAP_SCALE;
+        assert!(gap_color_step(narrow, base) < gap_color_step(base, base));
+    }
+
+    #[test]
+    fn milestones_fire_once_each() {
+        let mut app = App::new();
+        app.add_event::<Action>()
+            .init_resource::<Milestones>()
+            .init_resource::<GameMode>()
+            .insert_resource(Score(450))
+            .add_system(score_milestones);
+
+        let fired = |app: &mut App| {
+            app.update();
+            let mut reader = app.world.resource::<Events<Action>>().get_reader();
+            reader
+                .iter(app.world.resource::<Events<Action>>())
+                .filter_map(|e| match e {
+                    Action::Milestone(points) => Some(*points),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(fired(&mut app), [200, 400]);
+        app.world.resource_mut::<Events<Action>>().clear();
+        assert!(fired(&mut app).is_empty());
+
+        app.world.resource_mut::<Score>().0 = 1000;
+        assert_eq!(fired(&mut app), [1000]);
+    }
+
+    #[test]
+    fn bounce_only_dies_after_several_hits() {
+        assert!(HitReaction::Die.is_fatal(1));
+        assert!(!HitReaction::Practice.is_fatal(100));
+        for hits in 1..BOUNCE_MAX_HITS {
+            assert!(!HitReaction::Bounce.is_fatal(hits));
+        }
+        assert!(HitReaction::Bounce.is_fatal(BOUNCE_MAX_HITS));
+    }
+
+    // 外部控制器直接发送的动作与打字触发的一样，移动鸟并播放音效
+    #[test]
+    fn injected_actions_move_the_birb_and_play_sounds() {
+        let sound = || Handle::weak(HandleId::random::<AudioSource>());
+        let audio_assets = AudioAssets {
+            menu: sound(),
+            game: sound(),
+            flap: sound(),
+            badflap: sound(),
+            score: sound(),
+            crash: sound(),
+            bump: sound(),
+            key: sound(),
+        };
+        let (flap, bump) = (audio_assets.flap.clone(), audio_assets.bump.clone());
+        let mut app = App::new();
+        app.add_event::<Action>()
+            .add_event::<PlaySfx>()
+            .insert_resource(audio_assets)
+            .init_resource::<FlapStep>()
+            .insert_resource(GameRng::new(1))
+            .add_system(update_target_position);
+        let start = Vec3::new(0., BIRB_START_Y, 0.);
+        let birb = app.world.spawn().insert(TargetPosition(start)).id();
+        let mut reader = app.world.resource::<Events<PlaySfx>>().get_reader();
+        let mut played = Vec::new();
+
+        for action in [Action::BirbUp, Action::BirbUp, Action::BirbDown] {
+            app.world.resource_mut::<Events<Action>>().send(action);
+            app.update();
+            let events = app.world.resource::<Events<PlaySfx>>();
+            played.extend(reader.iter(events).map(|sfx| sfx.source.clone()));
+        }
+
+        let step = FlapStep::default().0;
+        let target = app.world.get::<TargetPosition>(birb).unwrap().0;
+        assert!(target.distance(start + Vec3::Y * step) < 1e-5);
+        assert_eq!(played, vec![flap.clone(), flap.clone(), flap]);
+
+        // 飞到最高处时改为播放撞到边界的声音
+        app.world.get_mut::<TargetPosition>(birb).unwrap().0.y = BIRB_MAX_Y;
+        app.world
+            .resource_mut::<Events<Action>>()
+            .send(Action::BirbUp);
+        app.update();
+        let events = app.world.resource::<Events<PlaySfx>>();
+        let played: Vec<_> = reader.iter(events).map(|sfx| sfx.source.clone()).collect();
+        assert_eq!(played, vec![bump]);
+    }
+
+    #[test]
+    fn score_after_actions() {
+        assert_eq!(score_after(5, &Action::IncScore(3), 0), 8);
+        assert_eq!(score_after(5, &Action::BadFlap, 2), 3);
+        assert_eq!(score_after(1, &Action::BadFlap, 2), 0);
+        assert_eq!(score_after(5, &Action::BadFlap, 0), 5);
+        assert_eq!(score_after(5, &Action::BirbUp, 2), 5);
+        assert_eq!(
+            score_after(5, &Action::WordExpired, 2),
+            5 - WORD_EXPIRED_PENALTY
+        );
+    }
+
+    // 按帧送入的字符，记录每个字符出现在第几帧
+    #[derive(Default)]
+    struct Frame(usize);
+    #[derive(Default)]
+    struct FedKeys(Vec<(usize, char)>);
+
+    fn log_fed_keys(
+        mut events: EventReader<ReceivedCharacter>,
+        frame: Res<Frame>,
+        mut fed: ResMut<FedKeys>,
+    ) {
+        for event in events.iter() {
+            fed.0.push((frame.0, event.char));
+        }
+    }
+
+    // 每帧时钟前进 100 毫秒，按帧输入 frames 中的字符，'.' 表示这一帧没有输入
+    fn type_frames(app: &mut App, frames: &str) {
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        for (frame, c) in frames.chars().enumerate() {
+            app.world.resource_mut::<Frame>().0 = frame;
+            let elapsed = Duration::from_millis(100 * (frame as u64 + 1));
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + elapsed);
+            if c != '.' {
+                app.world
+                    .resource_mut::<Events<ReceivedCharacter>>()
+                    .send(ReceivedCharacter {
+                        id: WindowId::primary(),
+                        char: c,
+                    });
+            }
+            app.update();
+        }
+    }
+
+    #[test]
+    fn playback_reproduces_the_recorded_score() {
+        let target = || TypingTarget::new("abcd".into(), vec![Action::IncScore(1)]);
+        let frames = "a..b.x.c..";
+
+        let mut app = typing_app();
+        app.init_resource::<Time>()
+            .init_resource::<Recorder>()
+            .init_resource::<Playback>()
+            .init_resource::<Frame>()
+            .init_resource::<FedKeys>()
+            .add_system(record_keys.after("keyboard"))
+            .add_system(log_fed_keys.after("keyboard"));
+        app.world.spawn().insert(target());
+        type_frames(&mut app, frames);
+        let recording = app.world.resource::<Recorder>().recording.clone();
+        let score = app.world.resource::<Score>().0;
+        let typed = app.world.resource::<FedKeys>().0.clone();
+        assert_eq!(recording.keys.len(), 4);
+        // 每个字符记录下输入时的游戏时间
+        assert!(recording.keys.windows(2).all(|keys| keys[0].0 < keys[1].0));
+
+        let mut app = typing_app();
+        app.init_resource::<Time>()
+            .init_resource::<Recorder>()
+            .insert_resource(Playback::new(recording))
+            .init_resource::<Frame>()
+            .init_resource::<FedKeys>()
+            .add_system(play_keys.before("keyboard"))
+            .add_system(record_keys.after("keyboard"))
+            .add_system(log_fed_keys.after("keyboard"));
+        app.world.spawn().insert(target());
+        // 回放时玩家的输入被丢弃，录像中的字符在录制时的同一帧送出
+        type_frames(&mut app, &"z".repeat(frames.len()));
+
+        assert_eq!(app.world.resource::<FedKeys>().0, typed);
+        assert_eq!(app.world.resource::<Score>().0, score);
+        assert!(app.world.resource::<Recorder>().recording.keys.is_empty());
+    }
+
+    #[test]
+    fn overlapping_gaps_score_once_per_frame() {
+        let aabb = |x: f32| Aabb {
+            center: Vec3A::new(x, 0., 0.),
+            half_extents: Vec3A::splat(0.5),
+        };
+        let birb = aabb(0.);
+        let first = Entity::from_raw(1);
+        let second = Entity::from_raw(2);
+
+        // 同时碰到两个空隙，本帧只有第一个计分
+        let gaps = vec![(first, aabb(-0.6)), (second, aabb(0.6))];
+        assert_eq!(passed_gap(&birb, gaps), Some(first));
+
+        // 已经计分的空隙带有 Used，不再出现在查询结果中，下一帧才轮到另一个空隙
+        assert_eq!(passed_gap(&birb, vec![(second, aabb(0.6))]), Some(second));
+        assert_eq!(passed_gap(&birb, vec![(second, aabb(2.))]), None);
+    }
+
+    // 以固定的帧率运行 movement，返回鸟最后的位置和旋转角度
+    fn fly(fps: u32, seconds: f32) -> (Vec3, f32) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ScrollDir>()
+            .init_resource::<TimeScale>()
+            .add_system(movement);
+        let start = Vec3::new(0., BIRB_START_Y, 0.);
+        let birb = app
+            .world
+            .spawn()
+            .insert(Transform::from_translation(start))
+            .insert(CurrentRotationZ(0.))
+            .insert(TargetPosition(start + Vec3::Y * 0.4))
+            .id();
+
+        let now = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        let frames = (seconds * fps as f32).round() as u32;
+        for frame in 1..=frames {
+            let elapsed = Duration::from_secs_f64(frame as f64 / fps as f64);
+            let mut time = app.world.resource_mut::<Time>();
+            time.update_with_instant(now + elapsed);
+            app.update();
+        }
+
+        let translation = app.world.get::<Transform>(birb).unwrap().translation;
+        let rotation = app.world.get::<CurrentRotationZ>(birb).unwrap().0;
+        (translation, rotation)
+    }
+
+    #[test]
+    fn mirrored_birb_faces_left_and_tilts_the_same_way() {
+        let mirrored = ScrollDir(-1.);
+        let facing = mirrored.rotation(Quat::IDENTITY) * Vec3::X;
+        assert!(facing.distance(-Vec3::X) < 1e-5);
+
+        // 抬头时鸟嘴朝上，两个方向都一样
+        let tilt = Quat::from_rotation_z(0.3);
+        let nose = tilt * Vec3::X;
+        let mirrored_nose = mirrored.rotation(tilt) * Vec3::X;
+        assert!(mirrored_nose.distance(Vec3::new(-nose.x, nose.y, nose.z)) < 1e-5);
+
+        assert_eq!(ScrollDir::default().rotation(tilt), tilt);
+    }
+
+    #[test]
+    fn movement_is_frame_rate_independent() {
+        // 0.2 秒到达目标，之后恢复水平，到达的时刻不在 144fps 的帧边界上
+        let (translation, rotation) = fly(60, 0.5);
+        assert!((rotation - 0.1).abs() < 1e-4);
+
+        for fps in [30, 144] {
+            let (t, r) = fly(fps, 0.5);
+            assert!(t.distance(translation) < 1e-4, "{} fps: {}", fps, t);
+            assert!((r - rotation).abs() < 1e-4, "{} fps: {}", fps, r);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    luck::{GameRng, RngStream},
+    AppState,
+};
+
+// 粒子插件：撞击和得分时在鸟的位置喷出一团小方块
+pub struct ParticlesPlugin;
+
+// 粒子爆发的种类
+#[derive(Clone, Copy)]
+pub enum BurstKind {
+    Crash, // 撞到障碍物
+    Score, // 穿过空隙
+}
+
+// 在某个位置产生一次粒子爆发
+pub struct ParticleBurst {
+    pub position: Vec3,
+    pub kind: BurstKind,
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec3,
+    lifetime: Timer,
+}
+
+// 粒子共用的网格和材质
+struct ParticleAssets {
+    mesh: Handle<Mesh>,
+    crash: Handle<StandardMaterial>,
+    score: Handle<StandardMaterial>,
+}
+
+// 粒子受到的重力
+const GRAVITY: f32 = -6.;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleBurst>()
+            .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup))
+            .add_system(spawn_particles)
+            .add_system(update_particles);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ParticleAssets {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.1 })),
+        crash: materials.add(StandardMaterial {
+            base_color: Color::rgb(1.0, 0.5, 0.1),
+            unlit: true,
+            ..Default::default()
+        }),
+        score: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.5, 1.0, 0.4),
+            unlit: true,
+            ..Default::default()
+        }),
+    });
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    mut events: EventReader<ParticleBurst>,
+    assets: Option<Res<ParticleAssets>>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    let assets = match assets {
+        Some(assets) => assets,
+        None => return,
+    };
+
+    // 粒子使用自己的子流，不会影响障碍物等其它随机数
+    let rng = game_rng.stream(RngStream::Particles);
+
+    for burst in events.iter() {
+        // 数量、速度、存活时间和材质
+        let (count, speed, lifetime, material) = match burst.kind {
+            BurstKind::Crash => (24, 2.0..5.0, 0.8, assets.crash.clone()),
+            BurstKind::Score => (8, 1.0..2.0, 0.4, assets.score.clone()),
+        };
+
+        for _ in 0..count {
+            let direction = Vec3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+            .normalize_or_zero();
+
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(burst.position),
+                    ..Default::default()
+                })
+                .insert(Particle {
+                    velocity: direction * rng.gen_range(speed.clone()),
+                    lifetime: Timer::from_seconds(lifetime, false),
+                });
+        }
+    }
+}
+
+// 粒子受重力下落并逐渐缩小，存活时间结束后销毁
+fn update_particles(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Particle)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut transform, mut particle) in query.iter_mut() {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y += GRAVITY * delta;
+        transform.translation += particle.velocity * delta;
+        transform.scale = Vec3::splat(1. - particle.lifetime.percent());
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::{prelude::*, utils::HashSet, window::WindowId};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    difficulty::{Difficulty, RunDifficulty},
+    luck::{GameRng, RngStream, Seed},
+    save::{load_ron, save_ron, Settings},
+    typing::{KeyboardLayout, TypingTarget, WordList},
+    AppState, Score,
+};
+
+// 录像插件：记录每一局输入的字符，游戏结束时保存到 replay.ron
+// 回放时按原来的时间把这些字符重新送入 keyboard，使用相同的种子生成障碍物和单词
+pub struct RecordingPlugin;
+
+const REPLAY_FILE: &str = "replay.ron";
+
+// 一局的录像
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recording {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub category: String,
+    pub keys: Vec<(f32, char)>, // 输入的字符和输入时的游戏时间（秒），暂停时不计时
+    pub score: u32,             // 录像时的最终分数，回放结束后用来比较
+}
+
+// 正在录制的录像
+#[derive(Default)]
+pub struct Recorder {
+    pub recording: Recording,
+    seconds: f32,
+}
+
+// 正在回放的录像，回放结束后清空
+#[derive(Default)]
+pub struct Playback {
+    recording: Option<Recording>,
+    next: usize,  // 下一个要送出的字符
+    seconds: f32, // 回放的游戏时间
+}
+impl Playback {
+    // 设置环境变量 TYPEY_BIRB_REPLAY 或使用 --replay 参数回放上一局的录像
+    fn from_env() -> Self {
+        if !crate::switch("TYPEY_BIRB_REPLAY", "--replay") {
+            return Self::default();
+        }
+
+        let recording: Recording = load_ron(REPLAY_FILE);
+        if recording.keys.is_empty() {
+            warn!("No replay to play back");
+            return Self::default();
+        }
+
+        Self::new(recording)
+    }
+
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording: Some(recording),
+            ..Default::default()
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>()
+            .insert_resource(Playback::from_env())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(start_run.label("start_run").before("apply_difficulty")),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(play_keys.before("keyboard"))
+                    .with_system(record_keys.after("keyboard")),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::EndScreen).with_system(finish_run))
+            .add_system_set(SystemSet::on_exit(AppState::EndScreen).with_system(end_playback));
+    }
+}
+
+// 新的一局：选择种子，重新创建 GameRng，开始录制
+// 设置了 TYPEY_BIRB_SEED 时使用该种子，否则每局随机选择
+// 回放时使用录像的种子、难度和单词分类，难度只用于这一局，不改变保存的设置
+#[allow(clippy::too_many_arguments)]
+fn start_run(
+    seed: Res<Seed>,
+    playback: Res<Playback>,
+    mut game_rng: ResMut<GameRng>,
+    mut recorder: ResMut<Recorder>,
+    (settings, mut run_difficulty): (Res<Settings>, ResMut<RunDifficulty>),
+    mut wordlist: ResMut<WordList>,
+    layout: Res<KeyboardLayout>,
+    mut query: Query<&mut TypingTarget>,
+) {
+    let run_seed = match &playback.recording {
+        Some(recording) => {
+            run_difficulty.0 = recording.difficulty;
+            wordlist.set_category(&recording.category);
+            recording.seed
+        }
+        None => {
+            run_difficulty.0 = settings.difficulty;
+            seed.0.unwrap_or_else(|| thread_rng().gen())
+        }
+    };
+    *game_rng = GameRng::new(run_seed);
+
+    // 屏幕上的单词也换成按种子选出的单词，保证回放时单词相同
+    // 与 typing.rs 中的 set_category 一样避开其他单词中的字母
+    wordlist.reseed(game_rng.fork(RngStream::Words));
+    let mut not: HashSet<char> = HashSet::default();
+    for mut target in query.iter_mut().filter(|t| !t.is_whole()) {
+        let next = wordlist.find_next_word(&not, &layout);
+        not.extend(next.chars());
+        target.replace(next);
+    }
+
+    *recorder = Recorder {
+        recording: Recording {
+            seed: game_rng.seed(),
+            difficulty: run_difficulty.0,
+            category: wordlist.category().to_owned(),
+            ..Default::default()
+        },
+        seconds: 0.,
+    };
+}
+
+// 记录本帧输入的字符
+pub fn record_keys(
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut recorder: ResMut<Recorder>,
+    playback: Res<Playback>,
+    time: Res<Time>,
+) {
+    recorder.seconds += time.delta_seconds();
+    if playback.active() {
+        char_input_events.clear();
+        return;
+    }
+
+    let seconds = recorder.seconds;
+    for event in char_input_events.iter() {
+        recorder.recording.keys.push((seconds, event.char));
+    }
+}
+
+// 按录像的时间送出字符，玩家本帧的输入被丢弃
+pub fn play_keys(
+    mut playback: ResMut<Playback>,
+    mut char_events: ResMut<Events<ReceivedCharacter>>,
+    time: Res<Time>,
+) {
+    if !playback.active() {
+        return;
+    }
+
+    char_events.clear();
+    playback.seconds += time.delta_seconds();
+
+    let playback = &mut *playback;
+    let keys = match &playback.recording {
+        Some(recording) => &recording.keys,
+        None => return,
+    };
+    while let Some((seconds, c)) = keys.get(playback.next) {
+        if *seconds > playback.seconds {
+            break;
+        }
+        char_events.send(ReceivedCharacter {
+            id: WindowId::primary(),
+            char: *c,
+        });
+        playback.next += 1;
+    }
+}
+
+// 游戏结束：保存录像；回放时比较分数
+// 回放到离开结束屏幕时才结束，结束屏幕上据此跳过最高分和排行榜
+fn finish_run(mut recorder: ResMut<Recorder>, playback: Res<Playback>, score: Res<Score>) {
+    match &playback.recording {
+        Some(recording) => {
+            if recording.score == score.0 {
+                info!("Replay finished with the recorded score {}", score.0);
+            } else {
+                warn!(
+                    "Replay finished with score {}, recorded {}",
+                    score.0, recording.score
+                );
+            }
+        }
+        None => {
+            recorder.recording.score = score.0;
+            save_ron(REPLAY_FILE, &recorder.recording);
+        }
+    }
+}
+
+// 离开结束屏幕时结束回放，下一局由玩家输入
+fn end_playback(mut playback: ResMut<Playback>) {
+    if playback.active() {
+        *playback = Playback::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typing::DEFAULT_CATEGORY;
+
+    #[test]
+    fn replay_difficulty_is_not_saved_to_settings() {
+        let mut app = App::new();
+        app.insert_resource(Seed(None))
+            .insert_resource(Playback::new(Recording {
+                seed: 7,
+                difficulty: Difficulty::Hard,
+                category: DEFAULT_CATEGORY.to_owned(),
+                ..Default::default()
+            }))
+            .insert_resource(GameRng::new(1))
+            .init_resource::<Recorder>()
+            .init_resource::<Settings>()
+            .init_resource::<RunDifficulty>()
+            .init_resource::<WordList>()
+            .init_resource::<KeyboardLayout>()
+            .add_system(start_run);
+        app.update();
+
+        assert_eq!(app.world.resource::<RunDifficulty>().0, Difficulty::Hard);
+        assert_eq!(
+            app.world.resource::<Settings>().difficulty,
+            Difficulty::default()
+        );
+        assert_eq!(app.world.resource::<GameRng>().seed(), 7);
+    }
+}
@@ -0,0 +1,355 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    audio::AudioSettings, difficulty::Difficulty, recording::Playback, ui::DEFAULT_FONT_SCALE,
+    AppState, BirbSkin, Score, BIRB_SCALE,
+};
+
+// 存档插件：保存最高分和设置
+pub struct SavePlugin;
+
+// 最高分
+#[derive(Default)]
+pub struct HighScore(pub u32);
+
+// 存档文件的内容
+// 缺少的字段使用默认值，旧版本的存档文件也能正常读取
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub high_score: u32,
+}
+impl SaveData {
+    // 读取存档，读取失败时使用默认值
+    pub fn load() -> Self {
+        load_ron(SAVE_FILE)
+    }
+
+    pub fn save(&self) {
+        save_ron(SAVE_FILE, self);
+    }
+}
+
+// 所有的游戏设置，单独保存在 settings.ron 中，任何一项改变时自动保存
+// 缺少的字段使用默认值，旧版本的设置文件也能正常读取
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub font_scale: f32, // 文字大小的缩放比例
+    pub difficulty: Difficulty,
+    pub gap_preview: bool, // 游戏中显示接下来几个空隙的位置，默认关闭
+    pub skin: BirbSkin,    // 玩家的鸟使用的模型
+    pub gap_colors: bool,  // 按空隙松紧给障碍物上色，默认关闭
+    pub birb_scale: f32,   // 鸟的模型缩放比例
+    pub mirrored: bool,    // 镜像模式，鸟向左飞
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            audio: AudioSettings::default(),
+            font_scale: DEFAULT_FONT_SCALE,
+            difficulty: Difficulty::default(),
+            gap_preview: false,
+            skin: BirbSkin::default(),
+            gap_colors: false,
+            birb_scale: BIRB_SCALE,
+            mirrored: false,
+        }
+    }
+}
+impl Settings {
+    // 读取设置，读取失败时使用默认值
+    // 还没有设置文件时，沿用旧版本存档中的音量和文字大小
+    pub fn load() -> Self {
+        if save_path(SETTINGS_FILE).is_some_and(|path| path.exists()) {
+            return load_ron(SETTINGS_FILE);
+        }
+
+        Self::from_legacy(load_ron(SAVE_FILE))
+    }
+
+    // 从旧版本存档中的设置迁移
+    fn from_legacy(legacy: LegacySettings) -> Self {
+        let mut settings = Self::default();
+        settings.audio.music_volume = legacy.music_volume;
+        settings.audio.sfx_volume = legacy.sfx_volume;
+        settings.font_scale = legacy.font_scale;
+        settings
+    }
+
+    pub fn save(&self) {
+        save_ron(SETTINGS_FILE, self);
+    }
+}
+
+// 旧版本的存档中同时保存了部分设置，缺少的字段使用当前的默认设置
+#[derive(Deserialize)]
+#[serde(default)]
+struct LegacySettings {
+    music_volume: f32,
+    sfx_volume: f32,
+    font_scale: f32,
+}
+impl Default for LegacySettings {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Self {
+            music_volume: settings.audio.music_volume,
+            sfx_volume: settings.audio.sfx_volume,
+            font_scale: settings.font_scale,
+        }
+    }
+}
+
+// 排行榜保留的记录数
+pub const LEADERBOARD_SIZE: usize = 10;
+
+// 排行榜中的一条记录
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub initials: String,
+    pub score: u32,
+    pub timestamp: u64, // 自 1970-01-01 起的秒数
+}
+
+// 本地排行榜，按分数从高到低排列，单独保存在 leaderboard.ron 中
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+impl Leaderboard {
+    pub fn load() -> Self {
+        load_ron(LEADERBOARD_FILE)
+    }
+
+    pub fn save(&self) {
+        save_ron(LEADERBOARD_FILE, self);
+    }
+
+    // 分数能否进入排行榜
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < LEADERBOARD_SIZE
+                || self.entries.iter().any(|e| score > e.score))
+    }
+
+    // 插入一条记录，分数相同时先到的排在前面
+    pub fn insert(&mut self, initials: String, score: u32) {
+        let index = self.entries.partition_point(|e| e.score >= score);
+        self.entries.insert(
+            index,
+            LeaderboardEntry {
+                initials,
+                score,
+                timestamp: timestamp(),
+            },
+        );
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+const SAVE_FILE: &str = "save.ron";
+const SETTINGS_FILE: &str = "settings.ron";
+const LEADERBOARD_FILE: &str = "leaderboard.ron";
+
+// 读取 ron 文件，读取失败时使用默认值
+pub fn load_ron<T: DeserializeOwned + Default>(file: &str) -> T {
+    let path = match save_path(file) {
+        Some(path) => path,
+        None => return T::default(),
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return T::default(),
+    };
+
+    match ron::from_str(&contents) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            T::default()
+        }
+    }
+}
+
+pub fn save_ron<T: Serialize>(file: &str, data: &T) {
+    let contents = match ron::ser::to_string_pretty(data, Default::default()) {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("Failed to serialize {}: {}", file, e);
+            return;
+        }
+    };
+
+    save_text(file, &contents);
+}
+
+// 把文本写入存档目录中的文件，网页版不保存
+pub fn save_text(file: &str, contents: &str) {
+    let path = match save_path(file) {
+        Some(path) => path,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(e) = std::fs::write(&path, contents) {
+        warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+// 存档文件位置，网页版不保存
+#[cfg(not(target_arch = "wasm32"))]
+fn save_path(file: &str) -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(dir.join("typey_birb").join(file))
+}
+#[cfg(target_arch = "wasm32")]
+fn save_path(_file: &str) -> Option<PathBuf> {
+    None
+}
+
+// 当前时间，网页版没有系统时间
+#[cfg(not(target_arch = "wasm32"))]
+pub fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+#[cfg(target_arch = "wasm32")]
+pub fn timestamp() -> u64 {
+    0
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScore(SaveData::load().high_score))
+            .insert_resource(Leaderboard::load())
+            .init_resource::<Settings>();
+
+        app.add_system_set(
+            SystemSet::on_exit(AppState::Loading).with_system(load_settings.label("load_settings")),
+        )
+        .add_system(save_settings)
+        .add_system_set(
+            SystemSet::on_enter(AppState::EndScreen)
+                .with_system(update_high_score.label("update_high_score")),
+        );
+    }
+}
+
+// 资源加载完成后读取设置，之前使用默认设置
+fn load_settings(mut settings: ResMut<Settings>) {
+    let loaded = Settings::load();
+    if *settings != loaded {
+        *settings = loaded;
+    }
+}
+
+// 设置改变时保存
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+// 游戏结束时更新并保存最高分，回放的分数不算
+fn update_high_score(
+    score: Res<Score>,
+    mut high_score: ResMut<HighScore>,
+    playback: Res<Playback>,
+) {
+    if playback.active() || score.0 <= high_score.0 {
+        return;
+    }
+
+    high_score.0 = score.0;
+    SaveData {
+        high_score: high_score.0,
+    }
+    .save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::Recording;
+
+    #[test]
+    fn replays_do_not_set_a_high_score() {
+        let mut app = App::new();
+        app.insert_resource(Score(50))
+            .insert_resource(HighScore(10))
+            .insert_resource(Playback::new(Recording::default()))
+            .add_system(update_high_score);
+        app.update();
+
+        assert_eq!(app.world.resource::<HighScore>().0, 10);
+    }
+
+    #[test]
+    fn missing_settings_fields_use_defaults() {
+        let settings: Settings = ron::from_str("(font_scale: 1.5)").unwrap();
+
+        assert_eq!(settings.font_scale, 1.5);
+        assert_eq!(settings.difficulty, Difficulty::default());
+        assert_eq!(settings.skin, BirbSkin::default());
+        assert!(settings.audio == AudioSettings::default());
+    }
+
+    #[test]
+    fn unknown_settings_fields_are_ignored() {
+        let settings: Settings =
+            ron::from_str("(gap_preview: true, removed_option: 3, audio: (old_volume: 0.1))")
+                .unwrap();
+
+        assert!(settings.gap_preview);
+        assert!(settings.audio == AudioSettings::default());
+    }
+
+    #[test]
+    fn legacy_save_settings_are_migrated() {
+        let legacy: LegacySettings =
+            ron::from_str("(high_score: 12, music_volume: 0.25, font_scale: 0.8)").unwrap();
+        let settings = Settings::from_legacy(legacy);
+
+        assert_eq!(settings.audio.music_volume, 0.25);
+        assert_eq!(
+            settings.audio.sfx_volume,
+            AudioSettings::default().sfx_volume
+        );
+        assert_eq!(settings.font_scale, 0.8);
+    }
+
+    #[test]
+    fn settings_survive_a_save_and_load() {
+        let mut settings = Settings {
+            difficulty: Difficulty::Hard,
+            gap_preview: true,
+            skin: BirbSkin::Gold,
+            font_scale: 1.25,
+            ..Default::default()
+        };
+        settings.audio.sfx_volume = 0.3;
+        settings.audio.muted = true;
+
+        let contents = ron::ser::to_string_pretty(&settings, Default::default()).unwrap();
+        let loaded: Settings = ron::from_str(&contents).unwrap();
+
+        // 静音不保存
+        settings.audio.muted = false;
+        assert!(loaded == settings);
+    }
+}
@@ -0,0 +1,296 @@
+use bevy::{
+    prelude::Mesh,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+// 圆锥体和胶囊体，与 cylinder.rs 中的圆柱体一样以 x轴和z轴 建立的平面为基础
+// 用于生成外形不同的障碍物
+
+/// A cone which stands on the XZ plane, with its apex pointing up
+pub struct Cone {
+    /// Radius of the base in the XZ plane.
+    /// 底面半径
+    pub radius: f32,
+    /// Height of the cone in the Y axis.
+    /// 高度
+    pub height: f32,
+    /// Number of vertices around the base.
+    /// 底面圆周的顶点数量
+    pub resolution: u32,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            height: 1.0,
+            resolution: 16,
+        }
+    }
+}
+
+impl From<Cone> for Mesh {
+    fn from(c: Cone) -> Self {
+        debug_assert!(c.radius > 0.0);
+        debug_assert!(c.height > 0.0);
+        debug_assert!(c.resolution > 2);
+
+        // 侧面每个切片一个底面顶点和一个顶点，再加上底面
+        let num_vertices = (c.resolution + 1) * 2 + c.resolution;
+        let num_indices = (c.resolution + (c.resolution - 2)) * 3;
+
+        let mut positions = Vec::with_capacity(num_vertices as usize);
+        let mut normals = Vec::with_capacity(num_vertices as usize);
+        let mut uvs = Vec::with_capacity(num_vertices as usize);
+        let mut indices = Vec::with_capacity(num_indices as usize);
+
+        let step_theta = std::f32::consts::TAU / c.resolution as f32;
+        let half_height = c.height / 2.;
+        // 侧面法线与水平面的夹角
+        let slope = c.radius.atan2(c.height);
+        let (normal_y, normal_xz) = slope.sin_cos();
+
+        // side 侧面，每个切片的顶点都单独生成，这样法线和 UV 都是对的
+        for segment in 0..=c.resolution {
+            let theta = segment as f32 * step_theta;
+            let (sin, cos) = theta.sin_cos();
+            let u = 1. - segment as f32 / c.resolution as f32;
+
+            positions.push([c.radius * cos, -half_height, c.radius * sin]);
+            normals.push([normal_xz * cos, normal_y, normal_xz * sin]);
+            uvs.push([u, 1.]);
+
+            positions.push([0., half_height, 0.]);
+            normals.push([normal_xz * cos, normal_y, normal_xz * sin]);
+            uvs.push([u, 0.]);
+        }
+
+        for i in 0..c.resolution {
+            let base = i * 2;
+            indices.extend_from_slice(&[base, base + 1, base + 2]);
+        }
+
+        // base 底面
+        let offset = positions.len() as u32;
+        for i in 0..c.resolution {
+            let theta = i as f32 * step_theta;
+            let (sin, cos) = theta.sin_cos();
+
+            positions.push([c.radius * cos, -half_height, c.radius * sin]);
+            normals.push([0., -1., 0.]);
+            uvs.push([0.5 * (cos + 1.), 1. - 0.5 * (sin + 1.)]);
+        }
+
+        for i in 1..(c.resolution - 1) {
+            indices.extend_from_slice(&[offset, offset + i, offset + i + 1]);
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh
+    }
+}
+
+/// A capsule which stands on the XZ plane: a cylinder with rounded ends
+pub struct Capsule {
+    /// Radius in the XZ plane.
+    /// 半径
+    pub radius: f32,
+    /// Total height of the capsule in the Y axis, including both ends.
+    /// 总高度，包括两端的半球
+    pub height: f32,
+    /// Number of vertices around each horizontal slice of the capsule.
+    /// 每个水平切片周围的顶点数量
+    pub resolution: u32,
+    /// Number of horizontal slices in each rounded end.
+    /// 每个半球的水平切片数量
+    pub latitudes: u32,
+}
+
+impl Default for Capsule {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            height: 2.0,
+            resolution: 16,
+            latitudes: 4,
+        }
+    }
+}
+
+impl From<Capsule> for Mesh {
+    fn from(c: Capsule) -> Self {
+        debug_assert!(c.radius > 0.0);
+        debug_assert!(c.height > 0.0);
+        debug_assert!(c.resolution > 2);
+        debug_assert!(c.latitudes > 0);
+
+        // 高度不足两个半径时，两端压扁成半椭球，保证总高度不变
+        let cap_height = c.radius.min(c.height / 2.);
+        let half_body = c.height / 2. - cap_height;
+
+        // 从下往上：底部半球，然后是顶部半球，中间两个赤道环之间是圆柱
+        let num_rings = (c.latitudes + 1) * 2;
+        let num_vertices = num_rings * (c.resolution + 1);
+        let num_indices = (num_rings - 1) * c.resolution * 6;
+
+        let mut positions = Vec::with_capacity(num_vertices as usize);
+        let mut normals = Vec::with_capacity(num_vertices as usize);
+        let mut uvs = Vec::with_capacity(num_vertices as usize);
+        let mut indices = Vec::with_capacity(num_indices as usize);
+
+        let step_theta = std::f32::consts::TAU / c.resolution as f32;
+        let step_phi = std::f32::consts::FRAC_PI_2 / c.latitudes as f32;
+
+        for ring in 0..num_rings {
+            // phi 是与 y 轴正方向的夹角，底部极点为 PI，顶部极点为 0
+            let (phi, center_y) = if ring <= c.latitudes {
+                (std::f32::consts::PI - ring as f32 * step_phi, -half_body)
+            } else {
+                let ring = ring - (c.latitudes + 1);
+                (
+                    std::f32::consts::FRAC_PI_2 - ring as f32 * step_phi,
+                    half_body,
+                )
+            };
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let y = center_y + cap_height * cos_phi;
+
+            for segment in 0..=c.resolution {
+                let theta = segment as f32 * step_theta;
+                let (sin, cos) = theta.sin_cos();
+
+                // 椭球面的法线
+                let normal = bevy::math::Vec3::new(
+                    sin_phi * cos / c.radius,
+                    cos_phi / cap_height,
+                    sin_phi * sin / c.radius,
+                )
+                .normalize();
+
+                positions.push([c.radius * sin_phi * cos, y, c.radius * sin_phi * sin]);
+                normals.push(normal.to_array());
+                uvs.push([
+                    1. - segment as f32 / c.resolution as f32,
+                    1. - (y + c.height / 2.) / c.height,
+                ]);
+            }
+        }
+
+        for i in 0..(num_rings - 1) {
+            let ring = i * (c.resolution + 1);
+            let next_ring = (i + 1) * (c.resolution + 1);
+
+            for j in 0..c.resolution {
+                indices.extend_from_slice(&[
+                    ring + j,
+                    next_ring + j,
+                    ring + j + 1,
+                    next_ring + j,
+                    next_ring + j + 1,
+                    ring + j + 1,
+                ]);
+            }
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    // 顶点、法线和 UV 数量一致，所有 UV 都在纹理范围内
+    fn assert_uvs(mesh: &Mesh) {
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
+        let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap().len();
+        assert_eq!(positions, normals);
+
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => {
+                assert_eq!(positions, uvs.len());
+                assert!(uvs
+                    .iter()
+                    .all(|[u, v]| (0.0..=1.0).contains(u) && (0.0..=1.0).contains(v)));
+            }
+            _ => panic!("expected Float32x2 uvs"),
+        }
+    }
+
+    // 索引都指向存在的顶点，返回三角形数量
+    fn triangle_count(mesh: &Mesh) -> usize {
+        let num_positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
+        let indices: Vec<usize> = mesh.indices().unwrap().iter().collect();
+
+        assert_eq!(indices.len() % 3, 0);
+        assert!(indices.iter().all(|&i| i < num_positions));
+        indices.len() / 3
+    }
+
+    #[test]
+    fn uv_count_matches_position_count() {
+        for resolution in [3, 4, 16, 64] {
+            let cone: Mesh = Cone {
+                resolution,
+                ..Default::default()
+            }
+            .into();
+            assert_uvs(&cone);
+
+            let capsule: Mesh = Capsule {
+                resolution,
+                ..Default::default()
+            }
+            .into();
+            assert_uvs(&capsule);
+        }
+
+        // 高度不足两个半径的胶囊体
+        let flat: Mesh = Capsule {
+            radius: 0.75,
+            height: 1.,
+            resolution: 16,
+            latitudes: 1,
+        }
+        .into();
+        assert_uvs(&flat);
+    }
+
+    #[test]
+    fn indices_in_range() {
+        for resolution in [3, 4, 16, 64] {
+            let mesh: Mesh = Cone {
+                resolution,
+                ..Default::default()
+            }
+            .into();
+            // 侧面加上底面的三角形数量
+            let resolution = resolution as usize;
+            assert_eq!(triangle_count(&mesh), resolution + (resolution - 2));
+        }
+
+        for resolution in [3, 4, 16, 64] {
+            for latitudes in [1, 4] {
+                let mesh: Mesh = Capsule {
+                    resolution,
+                    latitudes,
+                    ..Default::default()
+                }
+                .into();
+                // 相邻两个环之间每个切片两个三角形
+                let rings = (latitudes as usize + 1) * 2;
+                assert_eq!(triangle_count(&mesh), (rings - 1) * resolution as usize * 2);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+// 昼夜循环插件：游戏中天空颜色和阳光随时间变化
+pub struct SkyPlugin;
+
+// 一个完整的 白天→黄昏→夜晚→黎明 循环的时长（秒）
+const CYCLE_SECONDS: f32 = 240.;
+
+// 本局已经过的游戏时间，暂停时不计时
+#[derive(Default)]
+pub struct SkyCycle {
+    pub elapsed: f32,
+}
+
+// 循环中的一个阶段
+struct SkyPhase {
+    clear: Color,     // 天空颜色
+    light: Color,     // 光的颜色
+    illuminance: f32, // 光照强度
+    elevation: f32,   // 光线绕 x 轴的角度，越接近 0 越贴近地平线
+}
+
+// 依次为 白天、黄昏、夜晚、黎明，各占循环的四分之一，之间线性过渡
+const PHASES: [SkyPhase; 4] = [
+    SkyPhase {
+        clear: Color::rgb(177. / 255., 214. / 255., 222. / 255.),
+        light: Color::WHITE,
+        illuminance: 5000.,
+        elevation: -std::f32::consts::FRAC_PI_8,
+    },
+    SkyPhase {
+        clear: Color::rgb(0.85, 0.58, 0.45),
+        light: Color::rgb(1.0, 0.75, 0.5),
+        illuminance: 3000.,
+        elevation: -std::f32::consts::PI / 24.,
+    },
+    SkyPhase {
+        clear: Color::rgb(0.1, 0.12, 0.25),
+        light: Color::rgb(0.6, 0.7, 1.0),
+        illuminance: 1200.,
+        elevation: -std::f32::consts::FRAC_PI_4,
+    },
+    SkyPhase {
+        clear: Color::rgb(0.8, 0.68, 0.75),
+        light: Color::rgb(1.0, 0.85, 0.85),
+        illuminance: 3000.,
+        elevation: -std::f32::consts::PI / 24.,
+    },
+];
+
+// 光源的位置和角度，白天与 main.rs 中 setup 的初始设置相同
+pub fn sun_transform(elevation: f32) -> Transform {
+    Transform {
+        translation: Vec3::new(0.0, 2.0, 0.0),
+        rotation: Quat::from_rotation_x(elevation)
+            * Quat::from_rotation_y(std::f32::consts::PI / 8.),
+        ..Default::default()
+    }
+}
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkyCycle>()
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(advance_sky))
+            .add_system(update_sky);
+    }
+}
+
+fn advance_sky(mut cycle: ResMut<SkyCycle>, time: Res<Time>) {
+    cycle.elapsed += time.delta_seconds();
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::rgb(
+        a.r() + (b.r() - a.r()) * t,
+        a.g() + (b.g() - a.g()) * t,
+        a.b() + (b.b() - a.b()) * t,
+    )
+}
+
+// 按已经过的时间在相邻两个阶段之间插值
+// 重置 SkyCycle 后回到白天
+fn update_sky(
+    cycle: Res<SkyCycle>,
+    mut clear_color: ResMut<ClearColor>,
+    mut query: Query<(&mut DirectionalLight, &mut Transform)>,
+) {
+    if !cycle.is_changed() {
+        return;
+    }
+
+    let position = (cycle.elapsed / CYCLE_SECONDS).fract() * PHASES.len() as f32;
+    let index = position as usize % PHASES.len();
+    let t = position.fract();
+    let from = &PHASES[index];
+    let to = &PHASES[(index + 1) % PHASES.len()];
+
+    clear_color.0 = lerp_color(from.clear, to.clear, t);
+
+    for (mut light, mut transform) in query.iter_mut() {
+        light.color = lerp_color(from.light, to.light, t);
+        light.illuminance = from.illuminance + (to.illuminance - from.illuminance) * t;
+        *transform = sun_transform(from.elevation + (to.elevation - from.elevation) * t);
+    }
+}
@@ -0,0 +1,314 @@
+use bevy::{ecs::system::SystemState, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gap_color_step,
+    luck::NextGapBag,
+    place_obstacle,
+    save::{save_ron, timestamp},
+    AppState, Birb, Distance, DistanceToSpawn, GapColorMaterials, GapColors, GapSize,
+    MovingObstacle, Obstacle, ObstacleLayout, ObstaclePart, ObstaclePartQuery, Parked, Score,
+    ScrollDir, Speed, TargetPosition, Used,
+};
+
+// 快照插件：游戏中按 F6 保存当前局面，按 F7 回到保存的局面
+// 配合固定种子和录像，可以从同一个局面反复重现问题
+pub struct SnapshotPlugin;
+
+// 可以复现一局游戏的状态：分数、速度、飞行距离、距离下一个障碍物、鸟的目标位置、
+// 屏幕上的障碍物和空隙序列的位置
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    score: u32,
+    speed: f32,
+    max_speed: f32,
+    distance: f32, // 空隙随飞行距离缩小
+    distance_to_spawn: f32,
+    birb_target: Option<[f32; 3]>, // 还没有生成鸟时为 None
+    obstacles: Vec<SavedObstacle>,
+    gap_bag: NextGapBag,
+}
+
+// 屏幕上的一个障碍物：翻转前的水平位置、布局、上下移动的状态和已经计分或撞过的部分
+#[derive(Clone, Serialize, Deserialize)]
+struct SavedObstacle {
+    x: f32,
+    layout: ObstacleLayout,
+    moving: Option<MovingObstacle>,
+    used: Vec<ObstaclePart>,
+}
+
+impl GameSnapshot {
+    pub fn capture(world: &mut World) -> Self {
+        let birb_target = world
+            .query_filtered::<&TargetPosition, With<Birb>>()
+            .iter(world)
+            .next()
+            .map(|target| target.0.to_array());
+        let obstacles = capture_obstacles(world);
+        let speed = world.resource::<Speed>();
+
+        Self {
+            score: world.resource::<Score>().0,
+            speed: speed.current,
+            max_speed: speed.max,
+            distance: world.resource::<Distance>().0,
+            distance_to_spawn: world.resource::<DistanceToSpawn>().0,
+            birb_target,
+            obstacles,
+            gap_bag: world.resource::<NextGapBag>().clone(),
+        }
+    }
+
+    pub fn apply(&self, world: &mut World) {
+        world.resource_mut::<Score>().0 = self.score;
+        let mut speed = world.resource_mut::<Speed>();
+        speed.current = self.speed;
+        speed.max = self.max_speed;
+        world.resource_mut::<Distance>().0 = self.distance;
+        world.resource_mut::<DistanceToSpawn>().0 = self.distance_to_spawn;
+        *world.resource_mut::<NextGapBag>() = self.gap_bag.clone();
+        restore_obstacles(world, &self.obstacles);
+
+        if let Some(target) = self.birb_target {
+            let mut query = world.query_filtered::<&mut TargetPosition, With<Birb>>();
+            for mut position in query.iter_mut(world) {
+                position.0 = Vec3::from_array(target);
+            }
+        }
+    }
+}
+
+// 按位置从前到后记录屏幕上的障碍物
+fn capture_obstacles(world: &mut World) -> Vec<SavedObstacle> {
+    let dir = *world.resource::<ScrollDir>();
+    let mut used_parts = world.query_filtered::<&ObstaclePart, With<Used>>();
+    let mut obstacles = world.query_filtered::<(
+        &Transform,
+        &ObstacleLayout,
+        Option<&MovingObstacle>,
+        &Children,
+    ), (With<Obstacle>, Without<Parked>)>();
+
+    let mut saved = obstacles
+        .iter(world)
+        .map(|(transform, layout, moving, children)| SavedObstacle {
+            x: transform.translation.x * dir.0,
+            layout: *layout,
+            moving: moving.copied(),
+            used: children
+                .iter()
+                .filter_map(|child| used_parts.get(world, *child).ok().copied())
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    saved.sort_by(|a, b| a.x.total_cmp(&b.x));
+    saved
+}
+
+// 把保存的障碍物摆回对象池中的障碍物上，其余的放回对象池
+// 按保存的布局重新设置网格和碰撞盒，不消耗空隙序列中的随机数
+#[allow(clippy::type_complexity)]
+fn restore_obstacles(world: &mut World, saved: &[SavedObstacle]) {
+    let mut state: SystemState<(
+        Commands,
+        ResMut<Assets<Mesh>>,
+        Query<(Entity, &mut Transform, &mut Visibility, &Children), With<Obstacle>>,
+        ObstaclePartQuery,
+        (Res<GapColors>, Res<GapColorMaterials>, Res<GapSize>),
+        Res<ScrollDir>,
+    )> = SystemState::new(world);
+    let (
+        mut commands,
+        mut meshes,
+        mut obstacles,
+        mut part_query,
+        (gap_colors, gap_materials, base_gap),
+        dir,
+    ) = state.get_mut(world);
+
+    let mut saved = saved.iter();
+    for (entity, mut transform, mut visibility, children) in obstacles.iter_mut() {
+        let obstacle = match saved.next() {
+            Some(obstacle) => obstacle,
+            None => {
+                visibility.is_visible = false;
+                commands.entity(entity).insert(Parked);
+                continue;
+            }
+        };
+
+        let color = gap_colors
+            .0
+            .then(|| &gap_materials.0[gap_color_step(obstacle.layout.gap_size, base_gap.0)]);
+        place_obstacle(
+            &mut commands,
+            &mut meshes,
+            &mut part_query,
+            children,
+            &obstacle.layout,
+            color,
+        );
+        for child in children.iter() {
+            let used =
+                matches!(part_query.get(*child), Ok((_, part, ..)) if obstacle.used.contains(part));
+            if used {
+                commands.entity(*child).insert(Used);
+            }
+        }
+
+        // 上下移动的障碍物的高度由 moving_obstacle_movement 按相位计算
+        transform.translation.x = obstacle.x * dir.0;
+        transform.translation.y = 0.;
+        visibility.is_visible = true;
+        let mut entity = commands.entity(entity);
+        entity.remove::<Parked>().insert(obstacle.layout);
+        match obstacle.moving {
+            Some(moving) => entity.insert(moving),
+            None => entity.remove::<MovingObstacle>(),
+        };
+    }
+
+    state.apply(world);
+}
+
+// 最近一次按 F6 保存的快照
+#[derive(Default)]
+struct QuickSnapshot(Option<GameSnapshot>);
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuickSnapshot>().add_system_set(
+            SystemSet::on_update(AppState::Playing).with_system(quick_snapshot.exclusive_system()),
+        );
+    }
+}
+
+// F6 保存快照，同时写入存档目录，便于在测试中使用；F7 恢复
+fn quick_snapshot(world: &mut World) {
+    let keyboard = world.resource::<Input<KeyCode>>();
+    let (save, restore) = (
+        keyboard.just_pressed(KeyCode::F6),
+        keyboard.just_pressed(KeyCode::F7),
+    );
+
+    if save {
+        let snapshot = GameSnapshot::capture(world);
+        let file = format!("snapshot-{}.ron", timestamp());
+        save_ron(&file, &snapshot);
+        info!("Saved snapshot to {}", file);
+        world.resource_mut::<QuickSnapshot>().0 = Some(snapshot);
+    } else if restore {
+        match world.resource::<QuickSnapshot>().0.clone() {
+            Some(snapshot) => snapshot.apply(world),
+            None => info!("No snapshot to restore"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        difficulty::RunDifficulty, fill_obstacle_pool, spawn_obstacle, GameMode, ObstacleMaterial,
+        ObstacleMeshes, ObstacleSpacing, ObstacleVariety, SpacingRamp, MOVING_OBSTACLE_MIN_SCORE,
+    };
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .insert_resource(ObstacleMeshes {
+                flange: Handle::default(),
+            })
+            .insert_resource(ObstacleMaterial(Handle::default()))
+            .init_resource::<ObstacleSpacing>()
+            .init_resource::<SpacingRamp>()
+            .init_resource::<ScrollDir>()
+            .insert_resource(Score(MOVING_OBSTACLE_MIN_SCORE))
+            .init_resource::<Speed>()
+            .insert_resource(Distance(400.))
+            .insert_resource(DistanceToSpawn(0.))
+            .insert_resource(NextGapBag::with_seed(
+                GapSize::default().start_range(),
+                3.0,
+                1,
+            ))
+            .init_resource::<RunDifficulty>()
+            .insert_resource(ObstacleVariety(true))
+            .init_resource::<GameMode>()
+            .init_resource::<GapSize>()
+            .init_resource::<GapColors>()
+            .insert_resource(GapColorMaterials(Vec::new()))
+            .add_startup_system(fill_obstacle_pool)
+            .add_system(spawn_obstacle);
+        app.world
+            .spawn()
+            .insert(Birb)
+            .insert(TargetPosition(Vec3::new(0., 2., 0.)));
+        app
+    }
+
+    // 屏幕上的障碍物，按位置从前到后排列
+    fn on_screen(world: &mut World) -> Vec<(f32, ObstacleLayout)> {
+        let mut query = world
+            .query_filtered::<(&Transform, &ObstacleLayout), (With<Obstacle>, Without<Parked>)>();
+        let mut obstacles = query
+            .iter(world)
+            .map(|(transform, layout)| (transform.translation.x, *layout))
+            .collect::<Vec<_>>();
+        obstacles.sort_by(|a, b| a.0.total_cmp(&b.0));
+        obstacles
+    }
+
+    // 把屏幕上的障碍物往后移，再由 spawn_obstacle 生成下一个，返回新障碍物的布局
+    fn spawn_next(app: &mut App) -> ObstacleLayout {
+        let mut query = app
+            .world
+            .query_filtered::<&mut Transform, (With<Obstacle>, Without<Parked>)>();
+        for mut transform in query.iter_mut(&mut app.world) {
+            transform.translation.x -= 10.;
+        }
+        app.world.resource_mut::<DistanceToSpawn>().0 = 0.;
+        app.update();
+        on_screen(&mut app.world).last().unwrap().1
+    }
+
+    #[test]
+    fn restored_snapshot_rebuilds_obstacles_and_later_gaps() {
+        let mut app = app();
+        app.update();
+        spawn_next(&mut app);
+        let snapshot = GameSnapshot::capture(&mut app.world);
+        let obstacles = on_screen(&mut app.world);
+        assert_eq!(obstacles.len(), 2);
+        let expected = (0..3).map(|_| spawn_next(&mut app)).collect::<Vec<_>>();
+
+        // 继续游戏后状态都变了
+        let world = &mut app.world;
+        world.resource_mut::<Score>().0 = 50;
+        world.resource_mut::<Distance>().0 = 0.;
+        world.resource_mut::<DistanceToSpawn>().0 = 0.;
+        for mut target in world.query::<&mut TargetPosition>().iter_mut(world) {
+            target.0.y = 5.;
+        }
+
+        // 经过 RON 往返，与写入文件再读取相同
+        let ron = ron::to_string(&snapshot).unwrap();
+        let restored: GameSnapshot = ron::from_str(&ron).unwrap();
+        restored.apply(world);
+
+        assert_eq!(world.resource::<Score>().0, MOVING_OBSTACLE_MIN_SCORE);
+        assert_eq!(world.resource::<Distance>().0, 400.);
+        let target = world.query::<&TargetPosition>().single(world).0;
+        assert_eq!(target, Vec3::new(0., 2., 0.));
+        assert_eq!(on_screen(world), obstacles);
+        // 之后生成的障碍物与保存后继续游戏时相同，空隙大小按恢复的飞行距离计算
+        assert_eq!(
+            (0..3).map(|_| spawn_next(&mut app)).collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
@@ -1,42 +1,235 @@
+use std::{collections::VecDeque, ops::DerefMut};
+
 use bevy::{prelude::*, utils::HashSet};
 use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::{difficulty::RunDifficulty, flag, switch, AppState};
 
 // 输入plugin
 pub struct TypingPlugin;
 
+// 没有输错就完成一个单词的额外得分
+pub const PERFECT_WORD_BONUS: u32 = 3;
+
+// 长单词的额外得分：超过这个长度的部分，每个字母再加分
+const LONG_WORD_MIN_LEN: usize = 5;
+const LONG_WORD_BONUS_PER_LETTER: u32 = 1;
+
+// 完成一个单词时按长度给的额外得分
+// 每个字母本身已经通过 IncScore(1) 得分，这里让长单词更划算
+// 与穿过空隙的得分无关
+pub fn word_bonus(len: usize) -> u32 {
+    len.saturating_sub(LONG_WORD_MIN_LEN) as u32 * LONG_WORD_BONUS_PER_LETTER
+}
+
+// 最近出现过的单词最多记录多少个，不超过单词总数的一半
+const MAX_RECENT_WORDS: usize = 50;
+
+// 默认的单词分类
+pub const DEFAULT_CATEGORY: &str = "classic";
+
 // 单词列表
 pub struct WordList {
-    words: Vec<String>,
+    categories: Vec<(String, Vec<String>)>, // 所有分类的名字和单词
+    category: usize,                        // 当前选择的分类
+    words: Vec<String>,                     // 当前分类打乱顺序后的单词
     index: usize,
+    recent: VecDeque<String>, // 最近出现过的单词，优先选择不在其中的单词
+    rng: ChaCha12Rng,         // 打乱顺序用的随机数发生器，回放时使用录像的种子
 }
-// 从 crate::words::WORDS 里随机获取单词
+// 从 crate::words::CATEGORIES 里随机获取单词，默认使用 classic 分类
 impl Default for WordList {
     fn default() -> Self {
-        let mut words = crate::words::WORDS
-            .lines()
-            .map(|w| w.to_owned())
-            .filter(|w| w.chars().count() > 0)
-            .collect::<Vec<_>>();
-        words.shuffle(&mut thread_rng());
-        Self { words, index: 0 }
+        Self::from_categories(
+            crate::words::CATEGORIES
+                .iter()
+                .map(|(name, words)| (name.to_string(), normalize_words(words.lines())))
+                .collect(),
+        )
+        .with_category(DEFAULT_CATEGORY)
+    }
+}
+
+// 整理单词：去掉首尾空白、转为小写并统一为 NFC 形式，去掉重复的单词
+// 只保留由字母和数字组成的单词，带重音的字母也可以，避免出现空格、标点等无法输入的单词
+// 保持原来的顺序，打乱顺序在 WordList 中进行
+pub fn normalize_words<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::default();
+    lines
+        .map(|w| w.trim().to_lowercase().nfc().collect::<String>())
+        .filter(|w| !w.is_empty())
+        .filter(|w| w.chars().all(|c| c.is_alphanumeric()))
+        .filter(|w| seen.insert(w.clone()))
+        .collect()
+}
+
+// 键盘布局：生成的单词只使用布局中方便输入的字符
+// 默认允许所有字母和数字，包括带重音的字母
+// 设置环境变量 TYPEY_BIRB_LAYOUT 或使用 --layout 参数选择布局（qwerty、qwertz、azerty）
+// AZERTY 键盘上数字需要按 Shift，所以不出现含数字的单词
+// 设置 TYPEY_BIRB_AVOID_KEYS 可以再排除一些字符，例如 TYPEY_BIRB_AVOID_KEYS=qz
+#[derive(Default)]
+pub struct KeyboardLayout {
+    avoided: HashSet<char>,
+}
+impl KeyboardLayout {
+    pub fn from_env() -> Self {
+        let mut layout = Self::default();
+        if let Some(name) = flag::<String>("TYPEY_BIRB_LAYOUT", "--layout") {
+            match name.to_lowercase().as_str() {
+                "qwerty" | "qwertz" => {}
+                "azerty" => layout.avoid('0'..='9'),
+                other => warn!("Unknown keyboard layout {}", other),
+            }
+        }
+        if let Some(avoid) = flag::<String>("TYPEY_BIRB_AVOID_KEYS", "--avoid-keys") {
+            layout.avoid(avoid.to_lowercase().chars());
+        }
+        layout
+    }
+
+    // 不再使用这些字符
+    pub fn avoid(&mut self, chars: impl IntoIterator<Item = char>) {
+        self.avoided.extend(chars);
+    }
+
+    // 单词中的字符是否都方便输入
+    pub fn allows(&self, word: &str) -> bool {
+        word.chars()
+            .all(|c| c.is_alphanumeric() && !self.avoided.contains(&c))
     }
 }
 
 impl WordList {
+    // 多个分类的单词列表，默认选择第一个分类
+    pub fn from_categories(categories: Vec<(String, Vec<String>)>) -> Self {
+        let mut wordlist = Self {
+            categories,
+            category: 0,
+            words: vec![],
+            index: 0,
+            recent: VecDeque::new(),
+            rng: ChaCha12Rng::from_entropy(),
+        };
+        wordlist.select(0);
+        wordlist
+    }
+
+    // 换成新的随机数发生器并重新打乱当前分类，相同的种子得到相同的单词顺序
+    pub fn reseed(&mut self, rng: ChaCha12Rng) {
+        self.rng = rng;
+        self.select(self.category);
+    }
+
+    // 设置环境变量 TYPEY_BIRB_CATEGORY 或使用 --category 参数选择启动时的分类
+    pub fn from_env() -> Self {
+        let wordlist = Self::default();
+        match flag::<String>("TYPEY_BIRB_CATEGORY", "--category") {
+            Some(name) => wordlist.with_category(&name),
+            None => wordlist,
+        }
+    }
+
+    // 选择分类，找不到该分类时保持不变
+    pub fn with_category(mut self, name: &str) -> Self {
+        self.set_category(name);
+        self
+    }
+
+    // 切换分类，返回是否找到了该分类
+    pub fn set_category(&mut self, name: &str) -> bool {
+        match self.categories.iter().position(|(n, _)| n == name) {
+            Some(category) => {
+                if category != self.category {
+                    self.select(category);
+                }
+                true
+            }
+            None => {
+                warn!("Unknown word category {}", name);
+                false
+            }
+        }
+    }
+
+    // 当前分类的名字
+    pub fn category(&self) -> &str {
+        &self.categories[self.category].0
+    }
+
+    // 换成另一个分类的单词，重新打乱顺序
+    fn select(&mut self, category: usize) {
+        self.category = category;
+        self.words = self.categories[category].1.clone();
+        self.words.shuffle(&mut self.rng);
+        self.index = 0;
+        self.recent.clear();
+    }
+
     // 找到下一个单词
-    pub fn find_next_word(&mut self, not: &HashSet<char>) -> String {
-        loop {
+    // 优先选择最近没有出现过的单词，找不到时再使用最近出现过的
+    // 分类中的单词较少时可能所有单词都包含要避开的字母，这时选择重复字母最少的单词
+    // 包含键盘布局不方便输入的字符的单词跳过，只有全部单词都不方便输入时才使用
+    pub fn find_next_word(&mut self, not: &HashSet<char>, layout: &KeyboardLayout) -> String {
+        let overlap = |word: &str| word.chars().filter(|c| not.contains(c)).count();
+
+        // 最多尝试一轮，单词很少或者都与 not 有重复字母时不会一直循环
+        for _ in 0..self.words.len() {
             let next = self.advance_word();
-            if next.chars().all(|c| !not.contains(&c)) {
+            if overlap(&next) == 0 && !self.recent.contains(&next) && layout.allows(&next) {
+                self.remember(next.clone());
                 return next;
             }
         }
+
+        // 中途重新打乱顺序时，一轮下来不一定遇到所有单词，这时在整个列表中查找
+        // 方便输入的优先，然后是重复字母少的，其次是没有出现过或者最早出现过的
+        let next = match self.words.iter().min_by_key(|w| {
+            let recent = self.recent.iter().position(|r| r == *w);
+            (!layout.allows(w), overlap(w), recent)
+        }) {
+            Some(next) => next.clone(),
+            None => {
+                warn!("Word list \"{}\" is empty", self.category());
+                return String::new();
+            }
+        };
+        if !layout.allows(&next) {
+            warn!(
+                "No word in \"{}\" fits the keyboard layout, using \"{}\"",
+                self.category(),
+                next
+            );
+        } else if overlap(&next) > 0 {
+            warn!(
+                "No word in \"{}\" avoids the letters on screen, using \"{}\"",
+                self.category(),
+                next
+            );
+        }
+        self.remember(next.clone());
+        next
+    }
+
+    // 记录出现过的单词，超出数量时忘掉最早的
+    fn remember(&mut self, word: String) {
+        self.recent.retain(|w| *w != word);
+        self.recent.push_back(word);
+        while self.recent.len() > self.recent_len() {
+            self.recent.pop_front();
+        }
+    }
+
+    fn recent_len(&self) -> usize {
+        (self.words.len() / 2).min(MAX_RECENT_WORDS)
     }
 
     fn advance_word(&mut self) -> String {
         self.index += 1;
         if self.index >= self.words.len() {
-            self.words.shuffle(&mut thread_rng());
+            self.words.shuffle(&mut self.rng);
             self.index = 0;
         }
         self.words[self.index].clone()
@@ -49,15 +242,25 @@ pub struct TypingTarget {
     pub word_actions: Vec<crate::Action>,
     pub index: usize,
     pub word: String,
+    pub mistakes: u32,     // 输入这个单词时输错的次数
+    pub ignore_case: bool, // 是否不区分大小写，菜单单词显示为大写，按着 Shift 输入也可以
 }
 
+// 弹出提示中的打字目标，例如退出确认
+// 只要存在这样的目标，输入就只作用于它们，其他单词保持不变
+#[derive(Component)]
+pub struct ModalTarget;
+
 impl TypingTarget {
+    // 单词统一转换为 NFC 形式，带重音的字母尽量合成为一个字符
     pub fn new(word: String, actions: Vec<crate::Action>) -> Self {
         Self {
             letter_actions: actions,
             word_actions: vec![],
             index: 0,
-            word,
+            word: word.nfc().collect(),
+            mistakes: 0,
+            ignore_case: false,
         }
     }
     pub fn new_whole(word: String, actions: Vec<crate::Action>) -> Self {
@@ -65,28 +268,257 @@ impl TypingTarget {
             word_actions: actions,
             letter_actions: vec![],
             index: 0,
-            word,
+            word: word.nfc().collect(),
+            mistakes: 0,
+            ignore_case: true,
         }
     }
     pub fn current_char(&self) -> Option<char> {
         self.word.chars().nth(self.index)
     }
+    // 输入的字符是否匹配当前字母
+    pub fn accepts(&self, c: char) -> bool {
+        self.current_char().is_some_and(|next| {
+            next == c || (self.ignore_case && next.to_lowercase().eq(c.to_lowercase()))
+        })
+    }
     pub fn advance_char(&mut self) -> Option<char> {
         self.index += 1;
         self.current_char()
     }
     pub fn replace(&mut self, new: String) {
-        self.word = new;
+        self.word = new.nfc().collect();
         self.index = 0;
+        self.mistakes = 0;
+    }
+    // 输入的字符是否可能是当前字母分解后的第一个字符
+    // 例如当前字母为 é，先输入 e 再输入组合用的重音符号
+    pub fn could_compose(&self, c: char) -> bool {
+        self.current_char()
+            .is_some_and(|next| next != c && next.nfd().next() == Some(c))
+    }
+    // 整个单词输入完成后才触发动作的目标（如开始屏幕上的菜单单词）
+    pub fn is_whole(&self) -> bool {
+        self.letter_actions.is_empty()
+    }
+    // 已输入的比例，0 到 1，按字符而不是字节计算
+    // 空单词没有需要输入的字母，视为已完成
+    pub fn progress(&self) -> f32 {
+        let len = self.word.chars().count();
+        if len == 0 {
+            1.
+        } else {
+            self.index.min(len) as f32 / len as f32
+        }
+    }
+    // 是否已经输入完整个单词
+    pub fn is_complete(&self) -> bool {
+        self.index >= self.word.chars().count()
+    }
+}
+
+// 刚输完的单词还没有换成新单词时，先暂存之后输入的字符
+// 换成新单词后再依次输入，打字很快时同一帧里输入的下一个单词的字母不会丢失或算作输错
+#[derive(Default)]
+pub struct KeyBuffer {
+    chars: VecDeque<char>,
+}
+
+// 最多暂存的字符数，超过后直接按原来的方式匹配
+const MAX_BUFFERED_KEYS: usize = 16;
+
+// 把输入的字符整理为 NFC 形式
+// 有的输入法会先输入字母再输入组合用的重音符号，这时先保留字母，与后面的重音符号合成一个字符
+#[derive(Default)]
+pub struct InputComposer {
+    pending: Option<char>,
+}
+impl InputComposer {
+    // 输入一个字符，返回整理后可以用于匹配的字符
+    // could_compose 判断该字符是否可能与后面的重音符号合成
+    pub fn feed(&mut self, c: char, could_compose: impl Fn(char) -> bool) -> Vec<char> {
+        let mut out = vec![];
+
+        if let Some(base) = self.pending.take() {
+            if is_combining_mark(c) {
+                return [base, c].into_iter().nfc().collect();
+            }
+            out.push(base);
+        }
+
+        if could_compose(c) {
+            self.pending = Some(c);
+        } else {
+            out.extend(std::iter::once(c).nfc());
+        }
+        out
+    }
+}
+
+// 手柄模式：用手柄按键直接控制鸟上下飞，方便演示
+// 开启后游戏中不再通过打字控制鸟，键盘输入只作用于菜单单词
+// 设置环境变量 TYPEY_BIRB_GAMEPAD 或使用 --gamepad 参数开启
+pub struct GamepadMode(pub bool);
+impl GamepadMode {
+    pub fn from_env() -> Self {
+        Self(switch("TYPEY_BIRB_GAMEPAD", "--gamepad"))
+    }
+}
+
+// 自由输入：开启后键盘输入的字母直接记录下来，不再匹配单词
+// 用于在排行榜中输入名字缩写
+#[derive(Default)]
+pub struct TextCapture {
+    pub active: bool,
+    pub text: String,
+    pub max_len: usize,
+}
+impl TextCapture {
+    pub fn begin(&mut self, max_len: usize) {
+        self.active = true;
+        self.text.clear();
+        self.max_len = max_len;
+    }
+    pub fn is_complete(&self) -> bool {
+        self.text.chars().count() >= self.max_len
+    }
+}
+
+// 单词倒计时：游戏中的每个单词都有限时，超时后换成新单词并扣分
+// 设置环境变量 TYPEY_BIRB_WORD_TIMER 或使用 --word-timer 参数开启
+pub struct WordTimeLimit(pub bool);
+impl WordTimeLimit {
+    pub fn from_env() -> Self {
+        Self(switch("TYPEY_BIRB_WORD_TIMER", "--word-timer"))
+    }
+}
+
+// 单词的倒计时，与 TypingTarget 放在同一个实体上，只在游戏中存在
+// 单词换了以后按新单词的长度重新计时
+#[derive(Component)]
+pub struct WordTimer {
+    pub timer: Timer,
+    word: String, // 正在计时的单词
+}
+
+// 菜单单词（整个单词输入完才触发的目标）的字母是否也播放按键音
+// 默认关闭，避免菜单里太吵
+// 设置环境变量 TYPEY_BIRB_KEY_HIT_WHOLE_WORDS 或使用 --key-hit-whole-words 参数开启
+#[derive(Default)]
+pub struct KeyHitOnWholeWords(pub bool);
+impl KeyHitOnWholeWords {
+    pub fn from_env() -> Self {
+        Self(switch(
+            "TYPEY_BIRB_KEY_HIT_WHOLE_WORDS",
+            "--key-hit-whole-words",
+        ))
+    }
+}
+
+// 游戏中的单词是否也不区分大小写，默认区分
+// 设置环境变量 TYPEY_BIRB_IGNORE_CASE 或使用 --ignore-case 参数开启
+#[derive(Default)]
+pub struct IgnoreCase(pub bool);
+impl IgnoreCase {
+    pub fn from_env() -> Self {
+        Self(switch("TYPEY_BIRB_IGNORE_CASE", "--ignore-case"))
+    }
+}
+
+// 开始屏幕和结束屏幕上开始、重试游戏要输入的单词，默认为 "start" 和 "retry"
+// 设置环境变量 TYPEY_BIRB_START_WORD、TYPEY_BIRB_RETRY_WORD 或使用 --start-word、--retry-word 参数修改，例如换成其它语言的单词
+pub struct CommandWords {
+    pub start: String,
+    pub retry: String,
+}
+impl Default for CommandWords {
+    fn default() -> Self {
+        Self {
+            start: "start".to_string(),
+            retry: "retry".to_string(),
+        }
+    }
+}
+impl CommandWords {
+    pub fn from_env() -> Self {
+        // 菜单单词不区分大小写，统一保存为小写；空的值使用默认单词
+        let word = |env, arg, default: String| {
+            flag::<String>(env, arg)
+                .map(|s| s.to_lowercase())
+                .filter(|s| !s.is_empty())
+                .unwrap_or(default)
+        };
+        let defaults = Self::default();
+        Self {
+            start: word("TYPEY_BIRB_START_WORD", "--start-word", defaults.start),
+            retry: word("TYPEY_BIRB_RETRY_WORD", "--retry-word", defaults.retry),
+        }
+    }
+}
+
+// 游戏中每个按键的记录，供打字研究使用，只在开启 LogKeystrokes 时发送
+#[derive(Clone, Debug)]
+pub struct KeystrokeLogged {
+    pub char: char,
+    pub correct: bool, // 是否匹配了某个单词
+    pub elapsed: f32,  // 本局的游戏时间（秒），暂停时不计时
+}
+
+// 是否记录每个按键，关闭时 keyboard 不发送 KeystrokeLogged
+// 设置环境变量 TYPEY_BIRB_LOG_KEYSTROKES 或使用 --log-keystrokes 参数开启
+#[derive(Default)]
+pub struct LogKeystrokes(pub bool);
+impl LogKeystrokes {
+    pub fn from_env() -> Self {
+        Self(switch("TYPEY_BIRB_LOG_KEYSTROKES", "--log-keystrokes"))
+    }
+}
+
+// 本局的按键统计，只在游戏中计数
+#[derive(Default)]
+pub struct Keystrokes {
+    pub correct: u32,
+    pub wrong: u32,
+    pub seconds: f32,       // 本局的游戏时间，暂停时不计时
+    pub perfect_words: u32, // 没有输错就完成的单词数
+}
+
+// 每分钟输入的单词数，按每 5 个字母算一个单词
+#[derive(Default)]
+pub struct Wpm(pub f32);
+// 输入正确率，0 ~ 1
+pub struct Accuracy(pub f32);
+impl Default for Accuracy {
+    fn default() -> Self {
+        Self(1.)
     }
 }
 
 impl Plugin for TypingPlugin {
     fn build(&self, app: &mut App) {
         // 初始化单词资源
-        app.init_resource::<WordList>()
+        app.insert_resource(WordList::from_env())
+            .insert_resource(GamepadMode::from_env())
+            .insert_resource(WordTimeLimit::from_env())
+            .insert_resource(KeyboardLayout::from_env())
+            .insert_resource(IgnoreCase::from_env())
+            .insert_resource(CommandWords::from_env())
+            .insert_resource(LogKeystrokes::from_env())
+            .add_event::<KeystrokeLogged>()
             .add_system(new_words)
-            .add_system(keyboard);
+            .add_system_set(SystemSet::on_update(AppState::StartScreen).with_system(set_category))
+            .add_system(keyboard.label("keyboard"))
+            .init_resource::<TextCapture>()
+            .insert_resource(KeyHitOnWholeWords::from_env())
+            .init_resource::<Keystrokes>()
+            .init_resource::<Wpm>()
+            .init_resource::<Accuracy>()
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(gamepad))
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_stats))
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(start_word_timers))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(word_timers))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(stop_word_timers))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(update_stats));
     }
 }
 
@@ -95,19 +527,50 @@ fn new_words(
     mut events: EventReader<crate::Action>,
     mut query: Query<(Entity, &mut TypingTarget)>,
     mut wordlist: ResMut<WordList>,
+    layout: Res<KeyboardLayout>,
 ) {
     for e in events.iter() {
         if let crate::Action::NewWord(entity) = e {
             // build a list of characters to avoid for the next word,
             // skipping the word we're replacing.
-            let not: HashSet<char> = query
-                .iter()
-                .filter(|(e, _)| e != entity)
-                .flat_map(|(_, t)| t.word.chars())
-                .collect();
+            let others = query.iter().filter(|(e, _)| e != entity).map(|(_, t)| t);
+            let next = next_word(&mut wordlist, &layout, others);
 
             if let Ok((_, mut target)) = query.get_mut(*entity) {
-                let next = wordlist.find_next_word(&not);
+                target.replace(next);
+            }
+        }
+    }
+}
+
+// 为一个打字目标选择新的单词，避开其他单词中的字母
+pub fn next_word<'a>(
+    wordlist: &mut WordList,
+    layout: &KeyboardLayout,
+    others: impl Iterator<Item = &'a TypingTarget>,
+) -> String {
+    let not: HashSet<char> = others.flat_map(|t| t.word.chars()).collect();
+    wordlist.find_next_word(&not, layout)
+}
+
+// 处理开始屏幕上的分类选择，游戏中的单词立即换成新分类的单词
+fn set_category(
+    mut events: EventReader<crate::Action>,
+    mut query: Query<&mut TypingTarget>,
+    mut wordlist: ResMut<WordList>,
+    layout: Res<KeyboardLayout>,
+) {
+    for e in events.iter() {
+        if let crate::Action::SetCategory(name) = e {
+            if *name == wordlist.category() || !wordlist.set_category(name) {
+                continue;
+            }
+
+            // 与 new_words 一样避开其他单词中的字母，菜单单词除外
+            let mut not: HashSet<char> = HashSet::default();
+            for mut target in query.iter_mut().filter(|t| !t.is_whole()) {
+                let next = wordlist.find_next_word(&not, &layout);
+                not.extend(next.chars());
                 target.replace(next);
             }
         }
@@ -115,38 +578,567 @@ fn new_words(
 }
 
 // 键盘输入
-fn keyboard(
+#[allow(clippy::too_many_arguments)]
+pub fn keyboard(
     // EventReader 接收输入字符
     mut char_input_events: EventReader<ReceivedCharacter>,
-    mut query: Query<(Entity, &mut TypingTarget)>,
+    mut query: Query<(Entity, &mut TypingTarget, Option<&ModalTarget>)>,
     mut events: EventWriter<crate::Action>,
+    state: Res<State<AppState>>,
+    gamepad_mode: Res<GamepadMode>,
+    mut keystrokes: ResMut<Keystrokes>,
+    mut capture: ResMut<TextCapture>,
+    key_hit_on_whole_words: Res<KeyHitOnWholeWords>,
+    ignore_case: Res<IgnoreCase>,
+    (log_keystrokes, mut logged): (Res<LogKeystrokes>, EventWriter<KeystrokeLogged>),
+    mut composer: Local<InputComposer>,
+    mut buffer: Local<KeyBuffer>,
 ) {
+    // 暂停和设置界面中，输入不作用于单词
+    // 手柄模式下游戏中也不通过打字控制
+    if matches!(state.current(), AppState::Paused | AppState::Settings)
+        || (gamepad_mode.0 && *state.current() == AppState::Playing)
+    {
+        char_input_events.clear();
+        buffer.chars.clear();
+        return;
+    }
+
+    // 自由输入时只接受字母，退格删除最后一个字母
+    if capture.active {
+        buffer.chars.clear();
+        for event in char_input_events.iter() {
+            if event.char == '\u{8}' {
+                capture.text.pop();
+            } else if event.char.is_ascii_alphabetic() && !capture.is_complete() {
+                capture.text.push(event.char.to_ascii_uppercase());
+            }
+        }
+        return;
+    }
+
+    // 有弹出提示时只匹配提示中的单词
+    let modal = query.iter().any(|(_, _, m)| m.is_some());
+
+    // 按住 Ctrl 时收到的控制字符（例如放弃本局的 Ctrl+Q）不算打字
+    let mut chars = vec![];
+    for event in char_input_events.iter().filter(|e| !e.char.is_control()) {
+        chars.extend(composer.feed(event.char, |c| {
+            query
+                .iter()
+                .any(|(_, target, m)| (!modal || m.is_some()) && target.could_compose(c))
+        }));
+    }
+
+    // 不区分大小写时统一按小写匹配，单词列表读取时已经转换为小写
+    if ignore_case.0 {
+        chars = chars.into_iter().flat_map(char::to_lowercase).collect();
+    }
+
+    // 是否有输完的单词在等待换成新单词
+    let waiting = |query: &Query<(Entity, &mut TypingTarget, Option<&ModalTarget>)>| {
+        query
+            .iter()
+            .any(|(_, t, m)| (!modal || m.is_some()) && !t.is_whole() && t.is_complete())
+    };
+
+    // 新单词已经出现，先输入暂存的字符
+    if !waiting(&query) {
+        chars.splice(0..0, buffer.chars.drain(..));
+    }
+
     // 判断收到的字符是否匹配显示单词的每个字符
-    for event in char_input_events.iter() {
-        let mut ok = false;
+    for c in chars {
+        // 等待新单词时，不属于其他单词的字符先暂存，保持输入的顺序
+        if buffer.chars.len() < MAX_BUFFERED_KEYS && waiting(&query) {
+            let matches_other = query
+                .iter()
+                .any(|(_, t, m)| (!modal || m.is_some()) && t.accepts(c));
+            if !buffer.chars.is_empty() || !matches_other {
+                buffer.chars.push_back(c);
+                continue;
+            }
+        }
+
+        let mut targets: Vec<_> = query
+            .iter_mut()
+            .filter(|(_, _, m)| !modal || m.is_some())
+            .map(|(entity, target, _)| (entity, target))
+            .collect();
+        let result = type_char(&mut targets, c, key_hit_on_whole_words.0);
+
+        if *state.current() == AppState::Playing {
+            if result.ok {
+                keystrokes.correct += 1;
+            } else {
+                keystrokes.wrong += 1;
+            }
+            keystrokes.perfect_words += result.perfect_words;
+
+            if log_keystrokes.0 {
+                logged.send(KeystrokeLogged {
+                    char: c,
+                    correct: result.ok,
+                    elapsed: keystrokes.seconds,
+                });
+            }
+        }
+
+        events.send_batch(result.actions.into_iter());
+    }
+}
+
+// 输入一个字符的结果
+#[derive(Default)]
+pub struct TypeResult {
+    pub ok: bool,                    // 是否匹配了某个单词
+    pub perfect_words: u32,          // 没有输错就完成的单词数
+    pub actions: Vec<crate::Action>, // 需要发送的动作，按顺序发送
+}
+
+// 用一个字符匹配所有的打字目标，不依赖 Bevy 的系统，可以直接测试
+pub fn type_char<T: DerefMut<Target = TypingTarget>>(
+    targets: &mut [(Entity, T)],
+    c: char,
+    key_hit_on_whole_words: bool,
+) -> TypeResult {
+    let mut result = TypeResult::default();
+    let mut key_hit = false;
+
+    for (entity, target) in targets.iter_mut() {
+        if target.current_char().is_none() {
+            continue;
+        }
+
+        if !target.accepts(c) {
+            // 菜单单词输错时从头开始，这样屏幕上可以同时有多个菜单单词
+            if target.is_whole() && target.index > 0 {
+                target.index = 0;
+            }
+            if !target.is_whole() || !target.accepts(c) {
+                continue;
+            }
+        }
+
+        result.actions.extend(target.letter_actions.iter().cloned());
+
+        if !target.is_whole() || key_hit_on_whole_words {
+            key_hit = true;
+        }
+
+        if target.advance_char().is_none() {
+            if target.is_whole() {
+                // 菜单单词保持不变，可以再次输入
+                target.index = 0;
+            } else {
+                let bonus = word_bonus(target.word.chars().count());
+                if bonus > 0 {
+                    result.actions.push(crate::Action::IncScore(bonus));
+                }
+                // 一次都没有输错时额外加分
+                if target.mistakes == 0 {
+                    let perfect = crate::Action::IncScore(PERFECT_WORD_BONUS);
+                    result.actions.push(perfect);
+                    result.perfect_words += 1;
+                }
+                result.actions.push(crate::Action::NewWord(*entity));
+            }
+
+            result.actions.extend(target.word_actions.iter().cloned());
+        }
+
+        result.ok = true;
+    }
+
+    if key_hit {
+        result.actions.push(crate::Action::KeyHit { char: c });
+    }
+
+    if !result.ok {
+        // 输错算在正在输入的单词上
+        for (_, target) in targets.iter_mut() {
+            if !target.is_whole() && target.index > 0 {
+                target.mistakes += 1;
+            }
+        }
+        result.actions.push(crate::Action::BadFlap);
+    }
+
+    result
+}
+
+// 新的一局重新统计
+fn reset_stats(
+    mut keystrokes: ResMut<Keystrokes>,
+    mut wpm: ResMut<Wpm>,
+    mut accuracy: ResMut<Accuracy>,
+) {
+    *keystrokes = Keystrokes::default();
+    *wpm = Wpm::default();
+    *accuracy = Accuracy::default();
+}
+
+// 开始一局时为游戏中的单词加上倒计时，菜单单词除外
+fn start_word_timers(
+    mut commands: Commands,
+    query: Query<(Entity, &TypingTarget)>,
+    limit: Res<WordTimeLimit>,
+) {
+    if !limit.0 {
+        return;
+    }
+
+    for (entity, _) in query.iter().filter(|(_, t)| !t.is_whole()) {
+        commands.entity(entity).insert(WordTimer {
+            timer: Timer::from_seconds(0., false),
+            word: String::new(),
+        });
+    }
+}
+
+fn stop_word_timers(mut commands: Commands, query: Query<Entity, With<WordTimer>>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<WordTimer>();
+    }
+}
+
+// 推进单词的倒计时，超时后扣分并换成新单词
+// 暂停时不在 Playing 的更新中，不会计时
+fn word_timers(
+    mut query: Query<(Entity, &TypingTarget, &mut WordTimer)>,
+    mut events: EventWriter<crate::Action>,
+    run_difficulty: Res<RunDifficulty>,
+    time: Res<Time>,
+) {
+    for (entity, target, mut word_timer) in query.iter_mut() {
+        if word_timer.word != target.word {
+            let len = target.word.chars().count();
+            let seconds = run_difficulty.0.word_seconds(len);
+            word_timer.timer = Timer::from_seconds(seconds, false);
+            word_timer.word = target.word.clone();
+            continue;
+        }
+
+        word_timer.timer.tick(time.delta());
+        if word_timer.timer.just_finished() {
+            events.send(crate::Action::WordExpired);
+            events.send(crate::Action::NewWord(entity));
+            // 新单词可能与原来的相同，先重新计时
+            word_timer.timer.reset();
+        }
+    }
+}
+
+// 根据按键统计计算 WPM 和正确率
+fn update_stats(
+    mut keystrokes: ResMut<Keystrokes>,
+    mut wpm: ResMut<Wpm>,
+    mut accuracy: ResMut<Accuracy>,
+    time: Res<Time>,
+) {
+    keystrokes.seconds += time.delta_seconds();
+
+    let minutes = keystrokes.seconds / 60.;
+    if minutes > 0. {
+        wpm.0 = keystrokes.correct as f32 / 5. / minutes;
+    }
+
+    let total = keystrokes.correct + keystrokes.wrong;
+    if total > 0 {
+        accuracy.0 = keystrokes.correct as f32 / total as f32;
+    }
+}
+
+// 手柄输入：面部按键和十字键直接发送 BirbUp/BirbDown，不经过 TypingTarget
+fn gamepad(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut events: EventWriter<crate::Action>,
+    gamepad_mode: Res<GamepadMode>,
+) {
+    if !gamepad_mode.0 {
+        gamepad_events.clear();
+        return;
+    }
 
-        for (entity, mut target) in query.iter_mut() {
-            if let Some(next) = target.current_char() {
-                if next == event.char {
-                    for action in target.letter_actions.iter() {
-                        events.send(action.clone());
-                    }
+    for event in gamepad_events.iter() {
+        // 只处理按下，松开时数值为 0
+        let button = match event.event_type {
+            GamepadEventType::ButtonChanged(button, value) if value > 0.5 => button,
+            _ => continue,
+        };
 
-                    if target.advance_char().is_none() {
-                        events.send(crate::Action::NewWord(entity));
+        match button {
+            GamepadButtonType::South | GamepadButtonType::North | GamepadButtonType::DPadUp => {
+                events.send(crate::Action::BirbUp)
+            }
+            GamepadButtonType::East | GamepadButtonType::West | GamepadButtonType::DPadDown => {
+                events.send(crate::Action::BirbDown)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wordlist(words: Vec<String>) -> WordList {
+        WordList::from_categories(vec![(DEFAULT_CATEGORY.to_owned(), words)])
+    }
+
+    #[test]
+    fn progress_starts_at_zero() {
+        let target = TypingTarget::new("birb".into(), vec![]);
+
+        assert_eq!(target.progress(), 0.);
+        assert!(!target.is_complete());
+    }
+
+    #[test]
+    fn progress_partway_counts_chars() {
+        let mut target = TypingTarget::new("naïve".into(), vec![]);
+        target.advance_char();
+        target.advance_char();
+
+        assert_eq!(target.current_char(), Some('ï'));
+        assert_eq!(target.progress(), 0.4);
+        assert!(!target.is_complete());
+    }
+
+    #[test]
+    fn progress_at_completion() {
+        let mut target = TypingTarget::new("birb".into(), vec![]);
+        while target.advance_char().is_some() {}
+
+        assert_eq!(target.progress(), 1.);
+        assert!(target.is_complete());
+    }
+
+    #[test]
+    fn recent_words_are_not_repeated() {
+        let words: Vec<String> = (0..20).map(|i| format!("word{}", i)).collect();
+        let mut wordlist = wordlist(words.clone());
+        let window = wordlist.recent_len() + 1;
+        let draws: usize = 1000;
 
-                        for action in target.word_actions.iter() {
-                            events.send(action.clone());
-                        }
-                    }
+        let picked: Vec<String> = (0..draws)
+            .map(|_| wordlist.find_next_word(&HashSet::default(), &KeyboardLayout::default()))
+            .collect();
 
-                    ok = true;
+        // 相邻的 window 个单词互不相同
+        for run in picked.windows(window) {
+            let unique: HashSet<&String> = run.iter().collect();
+            assert_eq!(unique.len(), window);
+        }
+
+        // 每个单词出现的次数不超过预期
+        let max = draws.div_ceil(window);
+        for word in &words {
+            let count = picked.iter().filter(|w| *w == word).count();
+            assert!(count <= max, "{} picked {} times", word, count);
+        }
+    }
+
+    #[test]
+    fn recency_respects_excluded_chars() {
+        let words = vec!["abc".to_owned(), "def".to_owned(), "ghi".to_owned()];
+        let mut wordlist = wordlist(words);
+        let not: HashSet<char> = "abc".chars().collect();
+
+        for _ in 0..20 {
+            assert_ne!(
+                wordlist.find_next_word(&not, &KeyboardLayout::default()),
+                "abc"
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_cleans_word_list() {
+        let words = "\ncigar\n  Rebut \nCIGAR\n\t\nnaïve\nhello world\nrebut\nblush\nNAÏVE\n";
+
+        // 带重音的单词保留下来，大小写不同的重复单词去掉
+        let expected = vec!["cigar", "rebut", "naïve", "blush"];
+        assert_eq!(normalize_words(words.lines()), expected);
+    }
+
+    // 与 keyboard 一样逐个字符输入，返回是否完整地输入了单词
+    fn type_word(target: &mut TypingTarget, input: &str) -> bool {
+        let mut composer = InputComposer::default();
+        for ch in input.chars() {
+            for c in composer.feed(ch, |c| target.could_compose(c)) {
+                if !target.accepts(c) {
+                    return false;
                 }
+                target.advance_char();
             }
         }
+        target.is_complete()
+    }
+
+    #[test]
+    fn accented_word_matches_composed_input() {
+        let mut target = TypingTarget::new("cafe\u{301}".into(), vec![]);
+
+        assert_eq!(target.word.chars().count(), 4);
+        assert!(type_word(&mut target, "caf\u{e9}"));
+    }
+
+    #[test]
+    fn loaded_accented_word_can_be_typed_decomposed() {
+        // 单词列表中的 ï 是分解形式，输入法也先输入字母再输入重音符号
+        let mut wordlist = wordlist(normalize_words("nai\u{308}ve\n".lines()));
+        let word = wordlist.find_next_word(&HashSet::default(), &KeyboardLayout::default());
+        assert_eq!(word, "na\u{ef}ve");
+
+        let mut target = TypingTarget::new(word, vec![]);
+        assert!(type_word(&mut target, "nai\u{308}ve"));
+    }
+
+    #[test]
+    fn accented_word_matches_decomposed_input() {
+        let mut target = TypingTarget::new("caf\u{e9}".into(), vec![]);
+
+        assert!(type_word(&mut target, "cafe\u{301}"));
+    }
+
+    #[test]
+    fn plain_letter_does_not_match_accented() {
+        let mut target = TypingTarget::new("caf\u{e9}".into(), vec![]);
+
+        assert!(!type_word(&mut target, "cafex"));
+    }
+
+    #[test]
+    fn type_char_matches_only_the_right_target() {
+        let up = Entity::from_raw(0);
+        let down = Entity::from_raw(1);
+        let mut up_target = TypingTarget::new("ab".into(), vec![crate::Action::BirbUp]);
+        let mut down_target = TypingTarget::new("cd".into(), vec![crate::Action::BirbDown]);
+        let mut targets = [(up, &mut up_target), (down, &mut down_target)];
+
+        let result = type_char(&mut targets, 'c', false);
+        assert!(result.ok);
+        assert!(matches!(
+            result.actions.as_slice(),
+            [crate::Action::BirbDown, crate::Action::KeyHit { char: 'c' }]
+        ));
+
+        let result = type_char(&mut targets, 'x', false);
+        assert!(!result.ok);
+        assert!(matches!(result.actions[..], [crate::Action::BadFlap]));
+        assert_eq!(targets[0].1.mistakes, 0);
+        assert_eq!(targets[1].1.mistakes, 1);
+    }
+
+    #[test]
+    fn menu_words_accept_shifted_letters() {
+        let mut start = TypingTarget::new_whole("start".into(), vec![crate::Action::Start]);
+        assert!(type_word(&mut start, "STArt"));
+
+        let mut word = TypingTarget::new("ab".into(), vec![crate::Action::BirbUp]);
+        assert!(!type_word(&mut word, "A"));
+    }
+
+    #[test]
+    fn next_word_avoids_letters_of_other_targets() {
+        let mut wordlist = wordlist(vec!["abc".to_owned(), "def".to_owned()]);
+        let other = TypingTarget::new("cab".into(), vec![]);
+        let layout = KeyboardLayout::default();
+
+        for _ in 0..10 {
+            assert_eq!(
+                next_word(&mut wordlist, &layout, std::iter::once(&other)),
+                "def"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_word_is_complete() {
+        let target = TypingTarget::new(String::new(), vec![]);
+
+        assert_eq!(target.progress(), 1.);
+        assert!(target.is_complete());
+    }
+
+    #[test]
+    fn next_word_terminates_when_every_word_shares_letters() {
+        let mut wordlist = wordlist(vec!["ab".to_owned(), "ba".to_owned()]);
+        let not: HashSet<char> = "ab".chars().collect();
+
+        for _ in 0..10 {
+            let next = wordlist.find_next_word(&not, &KeyboardLayout::default());
+            assert!(next == "ab" || next == "ba");
+        }
+    }
+
+    #[test]
+    fn next_word_from_an_empty_list_is_empty() {
+        let mut wordlist = wordlist(vec![]);
+
+        assert_eq!(
+            wordlist.find_next_word(&HashSet::default(), &KeyboardLayout::default()),
+            ""
+        );
+    }
+
+    #[test]
+    fn next_word_skips_characters_the_layout_avoids() {
+        let mut wordlist = wordlist(vec!["abc".to_owned(), "de1".to_owned()]);
+        let mut layout = KeyboardLayout::default();
+        layout.avoid("0123456789".chars());
+
+        for _ in 0..10 {
+            assert_eq!(wordlist.find_next_word(&HashSet::default(), &layout), "abc");
+        }
 
-        if !ok {
-            events.send(crate::Action::BadFlap);
+        // 所有单词都不方便输入时仍然给出一个单词
+        layout.avoid("abc".chars());
+        let next = wordlist.find_next_word(&HashSet::default(), &layout);
+        assert!(next == "abc" || next == "de1");
+    }
+
+    #[test]
+    fn keys_typed_past_a_finished_word_go_to_the_next_word() {
+        use bevy::window::{ReceivedCharacter, WindowId};
+
+        let mut app = App::new();
+        app.add_state(AppState::Playing)
+            .add_event::<ReceivedCharacter>()
+            .add_event::<crate::Action>()
+            .insert_resource(wordlist(vec!["cd".to_owned()]))
+            .init_resource::<KeyboardLayout>()
+            .insert_resource(GamepadMode(false))
+            .init_resource::<Keystrokes>()
+            .init_resource::<TextCapture>()
+            .init_resource::<KeyHitOnWholeWords>()
+            .init_resource::<IgnoreCase>()
+            .init_resource::<LogKeystrokes>()
+            .add_event::<KeystrokeLogged>()
+            .add_system(keyboard.label("keyboard"))
+            .add_system(new_words.after("keyboard"));
+        let entity = app
+            .world
+            .spawn()
+            .insert(TypingTarget::new("ab".into(), vec![crate::Action::BirbUp]))
+            .id();
+
+        // 同一帧里输完 ab 又输入了下一个单词的 c
+        let mut events = app.world.resource_mut::<Events<ReceivedCharacter>>();
+        for c in "abc".chars() {
+            events.send(ReceivedCharacter {
+                id: WindowId::primary(),
+                char: c,
+            });
         }
+        app.update();
+        app.update();
+
+        let target = app.world.get::<TypingTarget>(entity).unwrap();
+        assert_eq!(target.word, "cd");
+        assert_eq!(target.index, 1);
+        assert_eq!(app.world.resource::<Keystrokes>().wrong, 0);
     }
 }
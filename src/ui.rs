@@ -1,30 +1,245 @@
 use crate::{
-    typing::{TypingTarget, WordList},
-    Action, AppState, FontAssets, GltfAssets, Score,
+    difficulty::Difficulty,
+    locale::Locale,
+    luck::{DailyChallenge, NextGapBag},
+    recording::Playback,
+    save::{HighScore, Leaderboard, Settings},
+    typing::{
+        Accuracy, CommandWords, KeyboardLayout, Keystrokes, ModalTarget, TextCapture, TypingTarget,
+        WordList, WordTimer, Wpm,
+    },
+    Action, AppState, Distance, FontAssets, GameMode, GapSize, GltfAssets, HitReaction, RivalRace,
+    RivalSkin, Score, ScrollDir, SCORE_MILESTONES,
+};
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    transform::TransformSystem,
+    ui::{update::ui_z_system, FocusPolicy},
+    utils::HashSet,
+    window::WindowResized,
 };
-use bevy::{prelude::*, utils::HashSet};
 
 // 定义 ui 插件
 pub struct UiPlugin;
 
+// 性能显示：帧率和帧时间，按 PERF_OVERLAY_KEY 切换，默认隐藏
+#[derive(Component)]
+struct PerfOverlay;
+const PERF_OVERLAY_KEY: KeyCode = KeyCode::F2;
+
+// 打字目标中下一个要输入的字母的颜色
+const NEXT_LETTER_COLOR: Color = Color::rgb(0.45, 0.85, 1.);
+
 #[derive(Component)]
 struct ScoreText;
+// 输错时单词短暂变红，记录原来的颜色以便恢复
+#[derive(Component)]
+struct Mistyped {
+    timer: Timer,
+    colors: Vec<Color>,
+}
+// 结束屏幕上重试或输入名字缩写的位置
+#[derive(Component)]
+struct EndScreenPrompt;
+// 输入中的名字缩写
+#[derive(Component)]
+struct InitialsText;
+// 顶部栏右侧实时显示 WPM 和正确率
+#[derive(Component)]
+struct StatsText;
 #[derive(Component)]
 struct StartScreen;
 #[derive(Component)]
 struct EndScreen;
+#[derive(Component)]
+struct SettingsScreen;
+#[derive(Component)]
+struct PauseScreen;
+// 开始屏幕上的退出确认
+#[derive(Component)]
+struct QuitPrompt;
+// 排行榜名字缩写的字母数
+const INITIALS_LEN: usize = 3;
+
+// 分数数字从旧值过渡到新值所用的时间
+const SCORE_TWEEN_SECONDS: f32 = 0.3;
+
+// 当前显示的分数，逐渐追上实际分数
+struct DisplayedScore {
+    value: f32,
+    from: f32, // 本次过渡的起始值
+    timer: Timer,
+}
+impl Default for DisplayedScore {
+    fn default() -> Self {
+        Self {
+            value: 0.,
+            from: 0.,
+            timer: Timer::from_seconds(SCORE_TWEEN_SECONDS, false),
+        }
+    }
+}
+
+// 单词下方的进度条
+#[derive(Component)]
+struct ProgressBar;
+// 单词上方的倒计时条，开启单词倒计时后随剩余时间缩短
+#[derive(Component)]
+struct TimerBar;
+// 开始屏幕上显示当前难度的文本
+#[derive(Component)]
+struct DifficultyText;
+// 开始屏幕上显示当前单词分类的文本
+#[derive(Component)]
+struct CategoryText;
+// 开始屏幕上显示练习模式是否开启的文本
+#[derive(Component)]
+struct PracticeText;
+// 开始屏幕上显示当前鸟的模型的文本
+#[derive(Component)]
+struct SkinText;
+// 设置界面中的一行，数字为行号
+#[derive(Component)]
+struct SettingsRow(usize);
+
+// 设置界面当前选中的行
+#[derive(Default)]
+struct SettingsSelection(usize);
+
+// 设置界面每一行的行号和总行数
+const MUSIC_ROW: usize = 0;
+const SFX_ROW: usize = 1;
+const TEXT_SIZE_ROW: usize = 2;
+const GAP_PREVIEW_ROW: usize = 3;
+const SETTINGS_ROWS: usize = 4;
+// 音量分为 0 ~ 9 共十档
+const VOLUME_STEPS: f32 = 9.;
+// 文字大小的调整范围和步长
+const FONT_SCALE_MIN: f32 = 0.75;
+const FONT_SCALE_MAX: f32 = 1.5;
+const FONT_SCALE_STEP: f32 = 0.25;
+
+// 默认的文字大小缩放比例，所有文字的 font_size 都会乘以 Settings 中的缩放比例
+pub const DEFAULT_FONT_SCALE: f32 = 1.;
+
+// 窗口比默认的 1280x720 小时文字按比例缩小，避免单词和分数被截断或重叠
+// 窗口更大时保持原来的大小
+const REFERENCE_WIDTH: f32 = 1280.;
+const REFERENCE_HEIGHT: f32 = 720.;
+const MIN_WINDOW_FIT: f32 = 0.5;
+
+// 按窗口大小得到的文字缩放比例，与 Settings 中的 font_scale 相乘
+struct WindowFit(f32);
+impl Default for WindowFit {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+// 游戏中顶部的比赛进度条，显示玩家和竞争鸟的相对位置
+#[derive(Component)]
+struct RaceBar;
+#[derive(Component)]
+struct PlayerMarker;
+#[derive(Component)]
+struct RivalMarker;
+// 进度条整体代表的距离，两只鸟相差超过一半时标记停在两端
+const RACE_BAR_SPAN: f32 = 40.;
+const RACE_MARKER_SIZE: f32 = 10.;
+
+// 分数达到里程碑时显示的横幅，计时结束后消失
+#[derive(Component)]
+struct MilestoneBanner(Timer);
+const MILESTONE_BANNER_SECONDS: f32 = 1.5;
+
+// 游戏中右侧的空隙预览，从左到右显示接下来几个障碍物空隙的高度
+#[derive(Component)]
+struct GapPreview;
+// 预览中的一个标记，数字为第几个空隙
+#[derive(Component)]
+struct GapMarker(usize);
+const GAP_PREVIEW_COUNT: usize = 5;
+const GAP_PREVIEW_WIDTH: f32 = 120.;
+const GAP_PREVIEW_HEIGHT: f32 = 80.;
+const GAP_MARKER_SIZE: f32 = 8.;
+
+// 切换画面时的渐变：离开一个状态时全屏变黑，进入新状态后逐渐显示出来
+const FADE_SECONDS: f32 = 0.3;
+// 渐变遮罩的 z 值，高于其他所有 UI，不超过 UI 相机的可见范围
+const FADE_OVERLAY_Z: f32 = 100.;
+
+// 渐变遮罩，覆盖整个窗口
+#[derive(Component)]
+struct FadeOverlay;
+
+// 渐变的进度，结束后遮罩隐藏
+pub struct Transition(Timer);
+impl Default for Transition {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(FADE_SECONDS, false);
+        timer.tick(timer.duration());
+        Self(timer)
+    }
+}
+
+// 文字创建时各段的原始 font_size，缩放时以此为基准，避免反复缩放产生误差
+#[derive(Component)]
+struct BaseFontSize(Vec<f32>);
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         // We need the font to have been loaded for this to work.
-        app.add_system(update_targets)// 增加 update_targets system
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .init_resource::<SettingsSelection>()
+            .init_resource::<DisplayedScore>()
+            .init_resource::<WindowFit>()
+            .init_resource::<Transition>()
+            .add_system(update_fade)
+            .add_system(toggle_perf_overlay)
+            .add_system(update_perf_overlay.after(toggle_perf_overlay))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                raise_fade_overlay
+                    .after(ui_z_system)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(fit_to_window.before(apply_font_scale))
+            .add_system(apply_font_scale)
+            .add_system(update_targets) // 增加 update_targets system
+            .add_system(flash_mistyped)
+            .add_system(add_progress_bars)
+            .add_system(update_progress_bars)
+            .add_system(add_timer_bars)
+            .add_system(update_timer_bars)
             .add_system(update_score) 
+            .add_system(hide_score_in_zen)
+            .add_system(update_stats_text)
             // 在进入 AppState::EndScreen 状态时，执行 death_screen
-            .add_system_set(SystemSet::on_enter(AppState::EndScreen).with_system(death_screen))
+            // 需要在更新最高分之前执行，才能判断是否破了纪录
+            .add_system_set(
+                SystemSet::on_enter(AppState::EndScreen)
+                    .with_system(death_screen.before("update_high_score")),
+            )
             // 在结束 AppState::Loading 状态时，执行 setup
-            .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(setup))
+            .add_system_set(
+                SystemSet::on_exit(AppState::Loading)
+                    // 语言可能换了字体，要在生成文字之前替换
+                    .with_system(setup.after("locale_font"))
+                    .with_system(spawn_fade_overlay)
+                    .with_system(spawn_perf_overlay.after("locale_font")),
+            )
             // 在进入AppState::StartScreen 状态时，执行 start_screen
             .add_system_set(SystemSet::on_enter(AppState::StartScreen).with_system(start_screen))
+            .add_system_set(
+                SystemSet::on_update(AppState::StartScreen)
+                    .with_system(update_difficulty_text)
+                    .with_system(update_category_text)
+                    .with_system(update_practice_text)
+                    .with_system(update_skin_text)
+                    .with_system(open_quit_prompt)
+                    .with_system(close_quit_prompt),
+            )
             // 在结束 AppState::StartScreen 状态时，执行 despawn_start_screen
             .add_system_set(
                 SystemSet::on_exit(AppState::StartScreen).with_system(despawn_start_screen),
@@ -32,12 +247,406 @@ impl Plugin for UiPlugin {
             // 在结束 AppState::EndScreen 状态时，执行 despawn_dead_screen
             .add_system_set(
                 SystemSet::on_exit(AppState::EndScreen).with_system(despawn_dead_screen),
-            );
+            )
+            .add_system_set(SystemSet::on_update(AppState::EndScreen).with_system(enter_initials))
+            // 设置界面
+            .add_system_set(SystemSet::on_enter(AppState::Settings).with_system(settings_screen))
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    .with_system(settings_input)
+                    .with_system(update_settings_rows.after(settings_input)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Settings).with_system(despawn_settings_screen),
+            )
+            // 比赛进度条，只在游戏中显示，每局重新生成
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_race_bar))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(update_race_bar.after("rival_race")),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(despawn_race_bar))
+            // 里程碑横幅，离开游戏时清除
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(show_milestone_banner)
+                    .with_system(update_milestone_banners),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing).with_system(despawn_milestone_banners),
+            )
+            // 空隙预览，在设置中开启后才显示
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(spawn_gap_preview))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(update_gap_preview.after("spawn_obstacle")),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(despawn_gap_preview))
+            // 暂停界面
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(pause_screen))
+            .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(despawn_pause_screen));
+
+        // 开始屏幕、游戏和结束屏幕之间切换时渐变，暂停和设置界面不渐变
+        for state in [
+            AppState::StartScreen,
+            AppState::Playing,
+            AppState::EndScreen,
+        ] {
+            app.add_system_set(SystemSet::on_exit(state.clone()).with_system(fade_out))
+                .add_system_set(SystemSet::on_enter(state).with_system(fade_in));
+        }
+    }
+}
+
+fn spawn_fade_overlay(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            // 不拦截鼠标，渐变结束后隐藏
+            focus_policy: FocusPolicy::Pass,
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(FadeOverlay);
+}
+
+// 离开状态时立即变黑，旧画面的 UI 在同一帧被消除，来不及逐渐变暗
+// 进入新状态之前渐变暂停
+fn fade_out(
+    mut transition: ResMut<Transition>,
+    mut query: Query<(&mut UiColor, &mut Visibility), With<FadeOverlay>>,
+) {
+    transition.0.reset();
+    transition.0.pause();
+
+    for (mut color, mut visibility) in query.iter_mut() {
+        color.0 = Color::BLACK;
+        visibility.is_visible = true;
+    }
+}
+
+// 进入新状态后开始变亮
+fn fade_in(mut transition: ResMut<Transition>) {
+    transition.0.reset();
+    transition.0.unpause();
+}
+
+fn update_fade(
+    mut transition: ResMut<Transition>,
+    mut query: Query<(&mut UiColor, &mut Visibility), With<FadeOverlay>>,
+    time: Res<Time>,
+) {
+    transition.0.tick(time.delta());
+
+    for (mut color, mut visibility) in query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        if transition.0.finished() {
+            visibility.is_visible = false;
+        } else {
+            color.0 = Color::rgba(0., 0., 0., transition.0.percent_left());
+        }
+    }
+}
+
+// bevy_ui 按实体顺序排列 UI 的前后，这里把遮罩移到最前面
+fn raise_fade_overlay(mut query: Query<&mut Transform, With<FadeOverlay>>) {
+    for mut transform in query.iter_mut() {
+        if transform.translation.z != FADE_OVERLAY_Z {
+            transform.translation.z = FADE_OVERLAY_Z;
+        }
+    }
+}
+
+fn spawn_race_bar(mut commands: Commands, mode: Res<GameMode>) {
+    // 禅模式下没有竞争鸟
+    if mode.is_zen() {
+        return;
+    }
+
+    let marker = |color: Color| NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Percent(50.),
+                ..Default::default()
+            },
+            size: Size::new(Val::Px(RACE_MARKER_SIZE), Val::Px(RACE_MARKER_SIZE)),
+            // 标记的中心对准位置
+            margin: UiRect {
+                left: Val::Px(-RACE_MARKER_SIZE / 2.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        color: color.into(),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(25.),
+                    top: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(50.), Val::Px(RACE_MARKER_SIZE)),
+                ..Default::default()
+            },
+            color: Color::rgba(1., 1., 1., 0.2).into(),
+            ..Default::default()
+        })
+        .insert(RaceBar)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(marker(Color::rgb_u8(255, 235, 146)))
+                .insert(RivalMarker);
+            parent
+                .spawn_bundle(marker(Color::WHITE))
+                .insert(PlayerMarker);
+        });
+}
+
+fn despawn_race_bar(mut commands: Commands, query: Query<Entity, With<RaceBar>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 两只鸟以中点为中心，按相差的距离分开，镜像模式下领先的一方在左边
+fn update_race_bar(
+    race: Res<RivalRace>,
+    mut player_query: Query<&mut Style, (With<PlayerMarker>, Without<RivalMarker>)>,
+    mut rival_query: Query<&mut Style, (With<RivalMarker>, Without<PlayerMarker>)>,
+    dir: Res<ScrollDir>,
+) {
+    let midpoint = (race.player_distance + race.rival_distance) / 2.;
+    let percent = |distance: f32| {
+        let x = 50. + dir.0 * (distance - midpoint) / RACE_BAR_SPAN * 100.;
+        Val::Percent(x.clamp(0., 100.))
+    };
+
+    for mut style in player_query.iter_mut() {
+        style.position.left = percent(race.player_distance);
+    }
+    for mut style in rival_query.iter_mut() {
+        style.position.left = percent(race.rival_distance);
+    }
+}
+
+fn show_milestone_banner(
+    mut commands: Commands,
+    mut events: EventReader<Action>,
+    font_assets: Res<FontAssets>,
+    locale: Res<Locale>,
+    query: Query<Entity, With<MilestoneBanner>>,
+) {
+    let mut milestone = None;
+    for e in events.iter() {
+        if let Action::Milestone(points) = e {
+            milestone = Some(*points);
+        }
+    }
+    let milestone = match milestone {
+        Some(points) => points,
+        None => return,
+    };
+
+    // 同时只显示最新的一个
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Percent(20.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        })
+        .insert(MilestoneBanner(Timer::from_seconds(
+            MILESTONE_BANNER_SECONDS,
+            false,
+        )))
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                format!("{} {}", milestone, locale.get("points")),
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 80.,
+                    color: Color::rgb_u8(255, 235, 146),
+                },
+            ));
+        });
+}
+
+// 横幅在最后三分之一的时间里淡出
+fn update_milestone_banners(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut MilestoneBanner, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, mut banner, children) in query.iter_mut() {
+        if banner.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = (banner.0.percent_left() * 3.).min(1.);
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+fn despawn_milestone_banners(mut commands: Commands, query: Query<Entity, With<MilestoneBanner>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_gap_preview(mut commands: Commands, settings: Res<Settings>) {
+    if !settings.gap_preview {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.),
+                    top: Val::Px(RACE_MARKER_SIZE + 10.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(GAP_PREVIEW_WIDTH), Val::Px(GAP_PREVIEW_HEIGHT)),
+                ..Default::default()
+            },
+            color: Color::rgba(1., 1., 1., 0.2).into(),
+            ..Default::default()
+        })
+        .insert(GapPreview)
+        .with_children(|parent| {
+            for i in 0..GAP_PREVIEW_COUNT {
+                // 越靠后的空隙颜色越淡
+                let alpha = 1. - i as f32 / GAP_PREVIEW_COUNT as f32 * 0.6;
+                let left = (i as f32 + 0.5) / GAP_PREVIEW_COUNT as f32 * GAP_PREVIEW_WIDTH;
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(left - GAP_MARKER_SIZE / 2.),
+                                bottom: Val::Percent(0.),
+                                ..Default::default()
+                            },
+                            size: Size::new(Val::Px(GAP_MARKER_SIZE), Val::Px(GAP_MARKER_SIZE)),
+                            ..Default::default()
+                        },
+                        color: Color::rgba(1., 1., 1., alpha).into(),
+                        ..Default::default()
+                    })
+                    .insert(GapMarker(i));
+            }
+        });
+}
+
+fn despawn_gap_preview(mut commands: Commands, query: Query<Entity, With<GapPreview>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 按空隙底部在坐标范围中的比例设置标记的高度
+// 预览不会取走空隙，生成障碍物后自动显示下一批，打乱顺序时也保持一致
+fn update_gap_preview(
+    mut bag: ResMut<NextGapBag>,
+    gap: Res<GapSize>,
+    mut query: Query<(&mut Style, &GapMarker)>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    let range = gap.start_range();
+    let gaps = bag.preview(GAP_PREVIEW_COUNT).collect::<Vec<_>>();
+    let usable = GAP_PREVIEW_HEIGHT - GAP_MARKER_SIZE;
+
+    for (mut style, marker) in query.iter_mut() {
+        let fraction = (gaps[marker.0] - range.start) / (range.end - range.start);
+        style.position.bottom = Val::Px(fraction.clamp(0., 1.) * usable);
+    }
+}
+
+// 递归消除暂停界面 UI实体
+fn despawn_pause_screen(mut commands: Commands, query: Query<Entity, With<PauseScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 暂停界面，覆盖在游戏之上
+fn pause_screen(mut commands: Commands, font_assets: Res<FontAssets>, locale: Res<Locale>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .insert(PauseScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                locale.get("paused"),
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 60.,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+// 递归消除设置界面 UI实体
+fn despawn_settings_screen(mut commands: Commands, query: Query<Entity, With<SettingsScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
     }
 }
 
 // 递归消除 dead screen时 UI实体
-fn despawn_dead_screen(mut commands: Commands, query: Query<Entity, With<EndScreen>>) {
+fn despawn_dead_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<EndScreen>>,
+    mut capture: ResMut<TextCapture>,
+) {
+    capture.active = false;
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -51,146 +660,721 @@ fn despawn_start_screen(mut commands: Commands, query: Query<Entity, With<StartS
 }
 
 // 使用 bevy_ui 中提供的 NodeBundle 和 TextBundle Widget 来创建 UI 实体
+#[allow(clippy::too_many_arguments)]
 fn start_screen(
     mut commands: Commands,
     gltf_assets: Res<GltfAssets>,
     font_assets: Res<FontAssets>,
+    daily: Res<DailyChallenge>,
+    high_score: Res<HighScore>,
+    leaderboard: Res<Leaderboard>,
+    settings: Res<Settings>,
+    command_words: Res<CommandWords>,
+    locale: Res<Locale>,
+    dir: Res<ScrollDir>,
 ) {
     // rival 竞争角色 创建实体
 
-    commands
-        .spawn_bundle(SceneBundle {
-            scene: gltf_assets.birb_gold.clone(),
-            transform: Transform::from_xyz(8.4, 4.0, -0.2)
-                .with_scale(Vec3::splat(2.5))
-                .with_rotation(Quat::from_euler(EulerRot::XYZ, -0.1, -2.5, -0.8)),
-            ..default()
-        })
-        .insert(StartScreen); // 插入开始屏幕组件
+    commands
+        .spawn_bundle(SceneBundle {
+            scene: settings.skin.rival().scene(&gltf_assets),
+            transform: dir.transform(
+                Transform::from_xyz(8.4, 4.0, -0.2)
+                    .with_scale(Vec3::splat(2.5))
+                    .with_rotation(Quat::from_euler(EulerRot::XYZ, -0.1, -2.5, -0.8)),
+            ),
+            ..default()
+        })
+        .insert(RivalSkin)
+        .insert(StartScreen); // 插入开始屏幕组件
+
+    // text 创建文本组件，使用 NodeBundle 作为容器，基于 Flexbox 布局
+
+    let container = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(0.),
+                    right: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(50.0), Val::Percent(70.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(StartScreen)
+        .id();
+    // 创建背景 Flexbox 容器
+    let bg = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(40.0)),
+                align_items: AlignItems::FlexStart,
+                justify_content: JustifyContent::SpaceBetween,
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            color: Color::BLACK.into(),
+            ..Default::default()
+        })
+        .id();
+
+    // 创建开始文本 Flexbox item，本游戏是以输入文字 start 开始的
+    let starttext = commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![TextSection {
+                    value: locale.get("start_prompt").into(),
+                    style: TextStyle {
+                        font: font_assets.main.clone(),
+                        font_size: 40.,
+                        color: Color::WHITE,
+                    },
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+
+    let starttarget = commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                ..Default::default()
+            },
+            text: Text {
+                sections: target_sections(
+                    &command_words.start.to_uppercase(),
+                    &font_assets.main,
+                    40.,
+                    Color::rgb_u8(255, 235, 146),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole(
+            command_words.start.clone(),
+            vec![Action::Start],
+        ))
+        .id();
+
+    // 输入 zen 开始一局禅模式
+    let zentarget = commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: target_sections(
+                    "ZEN",
+                    &font_assets.main,
+                    30.,
+                    Color::rgba(0.8, 0.8, 0.8, 1.0),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole(
+            "zen".into(),
+            vec![Action::StartZen],
+        ))
+        .id();
+
+    // 输入 options 打开设置
+    let optionstarget = commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: target_sections(
+                    "OPTIONS",
+                    &font_assets.main,
+                    30.,
+                    Color::rgba(0.8, 0.8, 0.8, 1.0),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole(
+            "options".into(),
+            vec![Action::Settings],
+        ))
+        .id();
+
+    // 输入 quit 打开退出确认
+    let quittarget = commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: target_sections(
+                    "QUIT",
+                    &font_assets.main,
+                    30.,
+                    Color::rgba(0.8, 0.8, 0.8, 1.0),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole("quit".into(), vec![Action::Quit]))
+        .id();
+
+    // 最高分
+    let besttext = commands
+        .spawn_bundle(TextBundle::from_section(
+            format!("{}: {}", locale.get("best"), high_score.0),
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .id();
+
+    // 创建实体
+    commands.entity(container).push_children(&[bg]);
 
-    // text 创建文本组件，使用 NodeBundle 作为容器，基于 Flexbox 布局
+    // 每日挑战标签
+    if daily.0 {
+        let dailytext = commands
+            .spawn_bundle(TextBundle {
+                text: Text::from_section(
+                    locale.get("daily_challenge"),
+                    TextStyle {
+                        font: font_assets.main.clone(),
+                        font_size: 40.,
+                        color: Color::rgb_u8(255, 235, 146),
+                    },
+                ),
+                ..Default::default()
+            })
+            .id();
+        commands.entity(bg).push_children(&[dailytext]);
+    }
 
-    let container = commands
+    // 难度选择：输入其中一个单词
+    let difficultytext = commands
+        .spawn_bundle(TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .insert(DifficultyText)
+        .id();
+    let difficultyrow = commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                position_type: PositionType::Absolute,
-                position: UiRect {
-                    bottom: Val::Px(0.),
-                    right: Val::Px(0.),
+                justify_content: JustifyContent::SpaceBetween,
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .id();
+    for d in Difficulty::ALL {
+        let target = commands
+            .spawn_bundle(TextBundle {
+                text: Text {
+                    sections: target_sections(
+                        &d.word().to_uppercase(),
+                        &font_assets.main,
+                        30.,
+                        Color::rgba(0.8, 0.8, 0.8, 1.0),
+                    ),
                     ..Default::default()
                 },
-                size: Size::new(Val::Percent(50.0), Val::Percent(70.0)),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            })
+            .insert(TypingTarget::new_whole(
+                d.word().into(),
+                vec![Action::SetDifficulty(d)],
+            ))
+            .id();
+        commands.entity(difficultyrow).push_children(&[target]);
+    }
+
+    // 单词分类选择：输入其中一个分类名
+    let categorytext = commands
+        .spawn_bundle(TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .insert(CategoryText)
+        .id();
+    let categoryrow = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::SpaceBetween,
+                size: Size::new(Val::Percent(100.0), Val::Auto),
                 ..Default::default()
             },
             color: Color::NONE.into(),
             ..Default::default()
         })
-        .insert(StartScreen)
         .id();
-    // 创建背景 Flexbox 容器
-    let bg = commands
+    for (name, _) in crate::words::CATEGORIES {
+        let target = commands
+            .spawn_bundle(TextBundle {
+                text: Text {
+                    sections: target_sections(
+                        &name.to_uppercase(),
+                        &font_assets.main,
+                        30.,
+                        Color::rgba(0.8, 0.8, 0.8, 1.0),
+                    ),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(TypingTarget::new_whole(
+                name.into(),
+                vec![Action::SetCategory(name)],
+            ))
+            .id();
+        commands.entity(categoryrow).push_children(&[target]);
+    }
+
+    // 练习模式：输入 practice 切换，左边显示撞到障碍物时的反应
+    let practicetext = commands
+        .spawn_bundle(TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .insert(PracticeText)
+        .id();
+    let practicetarget = commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: target_sections(
+                    "PRACTICE",
+                    &font_assets.main,
+                    30.,
+                    Color::rgba(0.8, 0.8, 0.8, 1.0),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole(
+            "practice".into(),
+            vec![Action::TogglePractice],
+        ))
+        .id();
+    let practicerow = commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Percent(70.0), Val::Percent(40.0)),
-                align_items: AlignItems::FlexStart,
                 justify_content: JustifyContent::SpaceBetween,
-                flex_direction: FlexDirection::ColumnReverse,
-                padding: UiRect::all(Val::Px(10.0)),
+                size: Size::new(Val::Percent(100.0), Val::Auto),
                 ..Default::default()
             },
-            color: Color::BLACK.into(),
+            color: Color::NONE.into(),
             ..Default::default()
         })
         .id();
+    commands
+        .entity(practicerow)
+        .push_children(&[practicetext, practicetarget]);
 
-    // 创建开始文本 Flexbox item，本游戏是以输入文字 start 开始的
-    let starttext = commands
+    // 鸟的模型：输入 skin 切换，开始屏幕上飞行的鸟立即换成新的模型
+    let skintext = commands
+        .spawn_bundle(TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .insert(SkinText)
+        .id();
+    let skintarget = commands
         .spawn_bundle(TextBundle {
+            text: Text {
+                sections: target_sections(
+                    "SKIN",
+                    &font_assets.main,
+                    30.,
+                    Color::rgba(0.8, 0.8, 0.8, 1.0),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(TypingTarget::new_whole(
+            "skin".into(),
+            vec![Action::NextSkin],
+        ))
+        .id();
+    let skinrow = commands
+        .spawn_bundle(NodeBundle {
             style: Style {
+                justify_content: JustifyContent::SpaceBetween,
+                size: Size::new(Val::Percent(100.0), Val::Auto),
                 ..Default::default()
             },
-            text: Text {
-                sections: vec![TextSection {
-                    value: "So you want to join the flock, eh?\nYou'll have to beat me first!\nType the word below when you're ready."
-                        .into(),
-                    style: TextStyle {
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .id();
+    commands
+        .entity(skinrow)
+        .push_children(&[skintext, skintarget]);
+
+    commands.entity(bg).push_children(&[
+        starttext,
+        starttarget,
+        difficultytext,
+        difficultyrow,
+        categorytext,
+        categoryrow,
+        practicerow,
+        skinrow,
+        zentarget,
+        optionstarget,
+        quittarget,
+        besttext,
+    ]);
+
+    // 排行榜，显示在左侧
+    if !leaderboard.entries.is_empty() {
+        let table = leaderboard
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| format!("{:>2}. {}  {}", i + 1, e.initials, e.score))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(20.),
+                        top: Val::Px(70.),
+                        ..Default::default()
+                    },
+                    flex_direction: FlexDirection::ColumnReverse,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..Default::default()
+            })
+            .insert(StartScreen)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle::from_section(
+                    locale.get("top_scores"),
+                    TextStyle {
                         font: font_assets.main.clone(),
-                        font_size: 40.,
+                        font_size: 30.,
+                        color: Color::rgb_u8(255, 235, 146),
+                    },
+                ));
+                parent.spawn_bundle(TextBundle::from_section(
+                    table,
+                    TextStyle {
+                        font: font_assets.main.clone(),
+                        font_size: 30.,
                         color: Color::WHITE,
                     },
-                }],
+                ));
+            });
+    }
+}
+
+// 输入 quit 后弹出退出确认，覆盖整个开始屏幕
+// 提示中的 yes 和 no 是 ModalTarget，打开时其他单词不接受输入
+fn open_quit_prompt(
+    mut commands: Commands,
+    mut events: EventReader<Action>,
+    query: Query<Entity, With<QuitPrompt>>,
+    font_assets: Res<FontAssets>,
+    locale: Res<Locale>,
+) {
+    if !events.iter().any(|e| matches!(e, Action::Quit)) || !query.is_empty() {
+        return;
+    }
+
+    let yes = spawn_quit_target(&mut commands, &font_assets, "yes", Action::ConfirmQuit);
+    let no = spawn_quit_target(&mut commands, &font_assets, "no", Action::CancelQuit);
+    let row = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::SpaceAround,
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .id();
+    commands.entity(row).push_children(&[yes, no]);
+
+    let text = commands
+        .spawn_bundle(TextBundle::from_section(
+            locale.get("quit_prompt"),
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 60.,
+                color: Color::WHITE,
+            },
+        ))
+        .id();
+    let panel = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(20.0)),
                 ..Default::default()
             },
+            color: Color::BLACK.into(),
             ..Default::default()
         })
         .id();
+    commands.entity(panel).push_children(&[text, row]);
 
-    let starttarget = commands
-        .spawn_bundle(TextBundle {
+    // 同时带有 StartScreen，离开开始屏幕时一起消除
+    commands
+        .spawn_bundle(NodeBundle {
             style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
                 ..Default::default()
             },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .insert(QuitPrompt)
+        .insert(StartScreen)
+        .push_children(&[panel]);
+}
+
+fn spawn_quit_target(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    word: &str,
+    action: Action,
+) -> Entity {
+    commands
+        .spawn_bundle(TextBundle {
             text: Text {
-                sections: vec![
-                    TextSection {
-                        value: "".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::GREEN,
-                        },
-                    },
-                    TextSection {
-                        value: "START".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::rgb_u8(255, 235, 146),
-                        },
-                    },
-                ],
+                sections: target_sections(
+                    &word.to_uppercase(),
+                    &font_assets.main,
+                    40.,
+                    Color::rgb_u8(255, 235, 146),
+                ),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(TypingTarget::new_whole("start".into(), vec![Action::Start]))
-        .id();
+        .insert(TypingTarget::new_whole(word.into(), vec![action]))
+        .insert(ModalTarget)
+        .id()
+}
 
-    // 创建实体
-    commands.entity(container).push_children(&[bg]);
-    commands.entity(bg).push_children(&[starttext, starttarget]);
+// 输入 no 或按 Esc 关闭退出确认
+fn close_quit_prompt(
+    mut commands: Commands,
+    mut events: EventReader<Action>,
+    keyboard: Res<Input<KeyCode>>,
+    query: Query<Entity, With<QuitPrompt>>,
+) {
+    let cancel = events.iter().any(|e| matches!(e, Action::CancelQuit));
+    if !cancel && !keyboard.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// 显示撞到障碍物时的反应
+fn update_practice_text(
+    mut query: Query<&mut Text, With<PracticeText>>,
+    reaction: Res<HitReaction>,
+    locale: Res<Locale>,
+) {
+    for mut text in query.iter_mut() {
+        let value = match *reaction {
+            HitReaction::Die => locale.get("on_hit_die"),
+            HitReaction::Bounce => locale.get("on_hit_bounce"),
+            HitReaction::Practice => locale.get("on_hit_practice"),
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value.to_string();
+        }
+    }
+}
+fn update_skin_text(
+    mut query: Query<&mut Text, With<SkinText>>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    for mut text in query.iter_mut() {
+        let value = format!(
+            "{}: {}",
+            locale.get("birb"),
+            settings.skin.name().to_uppercase()
+        );
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn update_difficulty_text(
+    mut query: Query<&mut Text, With<DifficultyText>>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    for mut text in query.iter_mut() {
+        let value = format!(
+            "{}: {}",
+            locale.get("difficulty"),
+            settings.difficulty.word().to_uppercase()
+        );
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// 显示当前选择的单词分类
+fn update_category_text(
+    mut query: Query<&mut Text, With<CategoryText>>,
+    wordlist: Res<WordList>,
+    locale: Res<Locale>,
+) {
+    for mut text in query.iter_mut() {
+        let value = format!(
+            "{}: {}",
+            locale.get("words"),
+            wordlist.category().to_uppercase()
+        );
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
 }
 
 // 游戏结束后的屏幕 ui 
+// 结束屏幕评价用到的打字速度和正确率界限
+const FAST_WPM: f32 = 60.;
+const SLOW_WPM: f32 = 25.;
+const SLOPPY_ACCURACY: f32 = 0.85;
+const PRECISE_ACCURACY: f32 = 0.98;
+
+// 结束屏幕上竞争鸟说的话
+// 先看速度、正确率和是否超过竞争鸟的组合，都不符合时按分数分成四档
+fn death_message(
+    locale: &Locale,
+    score: &Score,
+    accuracy: &Accuracy,
+    wpm: &Wpm,
+    beat_rival: bool,
+) -> String {
+    let [apprentice, close_one, wow] = SCORE_MILESTONES;
+    let fast = wpm.0 >= FAST_WPM;
+    let sloppy = accuracy.0 < SLOPPY_ACCURACY;
+    let precise = accuracy.0 >= PRECISE_ACCURACY;
+
+    if beat_rival && sloppy {
+        return locale.get("death_mashed").to_string();
+    }
+
+    let death_msg = if fast && sloppy {
+        "death_fast_sloppy"
+    } else if fast && precise {
+        "death_quick_clean"
+    } else if precise && wpm.0 < SLOW_WPM && score.0 > apprentice {
+        "death_slow_steady"
+    } else if score.0 > wow {
+        "death_wow"
+    } else if score.0 > close_one {
+        "death_close_one"
+    } else if score.0 > apprentice {
+        "death_apprentice"
+    } else {
+        "death_ouch"
+    };
+    let death_msg = locale.get(death_msg);
+
+    if beat_rival {
+        format!("{}\n{}", locale.get("death_beat_rival"), death_msg)
+    } else {
+        death_msg.to_string()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn death_screen(
     mut commands: Commands,
     gltf_assets: Res<GltfAssets>,
     font_assets: Res<FontAssets>,
     score: Res<Score>,
+    race: Res<RivalRace>,
+    // 回放的分数不算最高分，也不进入排行榜
+    (high_score, leaderboard, playback): (Res<HighScore>, Res<Leaderboard>, Res<Playback>),
+    mut capture: ResMut<TextCapture>,
+    wpm: Res<Wpm>,
+    accuracy: Res<Accuracy>,
+    keystrokes: Res<Keystrokes>,
+    distance: Res<Distance>,
+    settings: Res<Settings>,
+    command_words: Res<CommandWords>,
+    locale: Res<Locale>,
+    dir: Res<ScrollDir>,
 ) {
-    let death_msg = if score.0 > 1000 {
-        "I... wha... wow!\nWhat am I even doing with my life?\nThe flock is yours, if you'll have us!"
-    } else if score.0 > 400 {
-        "That was a close one!\nWith moves like that, you'll\nfit in well here!"
-    } else if score.0 > 200 {
-        "Not bad, kid!\nThere may be room for you in the flock\nas an unpaid apprentice."
+    // 此时最高分尚未更新
+    let new_best = !playback.active() && score.0 > high_score.0;
+    let best = if new_best { score.0 } else { high_score.0 };
+
+    let death_msg = death_message(&locale, &score, &accuracy, &wpm, race.beaten());
+    // 曾经超过竞争鸟但结束时没有领先，额外显示一行
+    let death_msg = if !race.beaten() && race.overtaken {
+        format!("{}\n{}", locale.get("death_passed_rival"), death_msg)
     } else {
-        "Oh wow, ouch!\nToo bad you're stuck at Z = 0.0,\nthe path is a bit clearer a few units over."
+        death_msg
     };
 
     // rival
 
     commands
         .spawn_bundle(SceneBundle {
-            scene: gltf_assets.birb_gold.clone(),
-            transform: Transform::from_xyz(8.4, 4.0, -0.2)
-                .with_scale(Vec3::splat(2.5))
-                .with_rotation(Quat::from_euler(EulerRot::XYZ, -0.1, -2.5, -0.8)),
+            scene: settings.skin.rival().scene(&gltf_assets),
+            transform: dir.transform(
+                Transform::from_xyz(8.4, 4.0, -0.2)
+                    .with_scale(Vec3::splat(2.5))
+                    .with_rotation(Quat::from_euler(EulerRot::XYZ, -0.1, -2.5, -0.8)),
+            ),
             ..default()
         })
+        .insert(RivalSkin)
         .insert(EndScreen);
 
     // text 创建文本 Flexbox item
@@ -238,7 +1422,7 @@ fn death_screen(
             },
             text: Text {
                 sections: vec![TextSection {
-                    value: death_msg.into(),
+                    value: death_msg,
                     style: TextStyle {
                         font: font_assets.main.clone(),
                         font_size: 40.,
@@ -250,53 +1434,309 @@ fn death_screen(
             ..Default::default()
         })
         .id();
-    // 创建 重试text Flexbox item
-    let retrytext = commands
+    // 重试或输入名字缩写的位置
+    // 分数能进入排行榜时先输入名字缩写，输入完成后再显示重试
+    let prompt = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(EndScreenPrompt)
+        .id();
+    if !playback.active() && leaderboard.qualifies(score.0) {
+        capture.begin(INITIALS_LEN);
+
+        let initialstext = commands
+            .spawn_bundle(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 40.,
+                    color: Color::rgb_u8(255, 235, 146),
+                },
+            ))
+            .insert(InitialsText)
+            .id();
+        commands.entity(prompt).push_children(&[initialstext]);
+    } else {
+        let retrytext = spawn_retry_target(&mut commands, &font_assets, &command_words);
+        commands.entity(prompt).push_children(&[retrytext]);
+    }
+    // 最高分
+    let besttext = commands
+        .spawn_bundle(TextBundle::from_section(
+            format!("{}: {}", locale.get("best"), best),
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .id();
+    // 本局的打字统计和飞行距离
+    let statstext = commands
+        .spawn_bundle(TextBundle::from_section(
+            format!(
+                "{} {:.0}  {} {:.0}%  {} {}\n{} {:.0}",
+                locale.get("wpm"),
+                wpm.0,
+                locale.get("accuracy"),
+                accuracy.0 * 100.,
+                locale.get("perfect_words"),
+                keystrokes.perfect_words,
+                locale.get("distance"),
+                distance.0
+            ),
+            TextStyle {
+                font: font_assets.main.clone(),
+                font_size: 30.,
+                color: Color::WHITE,
+            },
+        ))
+        .id();
+
+    commands.entity(container).push_children(&[bg]);
+
+    // 破纪录时在最上面显示横幅
+    if new_best {
+        let newbesttext = commands
+            .spawn_bundle(TextBundle::from_section(
+                locale.get("new_best"),
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 50.,
+                    color: Color::rgb_u8(255, 235, 146),
+                },
+            ))
+            .id();
+        commands.entity(bg).push_children(&[newbesttext]);
+    }
+
+    commands
+        .entity(bg)
+        .push_children(&[deadtext, prompt, statstext, besttext]);
+}
+
+// 重试按钮，输入 retry 重新开始
+fn spawn_retry_target(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    command_words: &CommandWords,
+) -> Entity {
+    commands
         .spawn_bundle(TextBundle {
             style: Style {
                 ..Default::default()
             },
             text: Text {
-                sections: vec![
-                    TextSection {
-                        value: "".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::GREEN,
-                        },
-                    },
-                    TextSection {
-                        value: "RETRY".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::rgb_u8(255, 235, 146),
-                        },
-                    },
-                ],
+                sections: target_sections(
+                    &command_words.retry.to_uppercase(),
+                    &font_assets.main,
+                    40.,
+                    Color::rgb_u8(255, 235, 146),
+                ),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(TypingTarget::new_whole("retry".into(), vec![Action::Retry]))
-        .id();
+        .insert(TypingTarget::new_whole(
+            command_words.retry.clone(),
+            vec![Action::Retry],
+        ))
+        .id()
+}
+
+// 输入名字缩写，输满后记入排行榜并显示重试
+#[allow(clippy::too_many_arguments)]
+fn enter_initials(
+    mut commands: Commands,
+    mut capture: ResMut<TextCapture>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut text_query: Query<(Entity, &mut Text), With<InitialsText>>,
+    prompt_query: Query<Entity, With<EndScreenPrompt>>,
+    score: Res<Score>,
+    font_assets: Res<FontAssets>,
+    command_words: Res<CommandWords>,
+    locale: Res<Locale>,
+) {
+    if !capture.active || !capture.is_changed() {
+        return;
+    }
+
+    if capture.is_complete() {
+        capture.active = false;
+        leaderboard.insert(capture.text.clone(), score.0);
+        leaderboard.save();
 
-    commands.entity(container).push_children(&[bg]);
-    commands.entity(bg).push_children(&[deadtext, retrytext]);
+        for (entity, _) in text_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for prompt in prompt_query.iter() {
+            let retrytext = spawn_retry_target(&mut commands, &font_assets, &command_words);
+            commands.entity(prompt).push_children(&[retrytext]);
+        }
+        return;
+    }
+
+    // 已输入的字母，其余位置显示下划线
+    let mut letters: Vec<String> = capture.text.chars().map(String::from).collect();
+    letters.resize(INITIALS_LEN, "_".into());
+    for (_, mut text) in text_query.iter_mut() {
+        text.sections[0].value =
+            format!("{} {}", locale.get("top_ten_initials"), letters.join(" "));
+    }
+}
+
+// 禅模式下隐藏分数
+fn hide_score_in_zen(mode: Res<GameMode>, mut query: Query<&mut Visibility, With<ScoreText>>) {
+    if !mode.is_changed() {
+        return;
+    }
+
+    for mut visibility in query.iter_mut() {
+        visibility.is_visible = !mode.is_zen();
+    }
 }
 
 // 更新分数
-fn update_score(mut query: Query<&mut Text, With<ScoreText>>, score: Res<Score>) {
-    if !score.is_changed() {
+// 分数变化时，显示的数字在 SCORE_TWEEN_SECONDS 内逐渐增加到新的分数
+fn update_score(
+    mut query: Query<&mut Text, With<ScoreText>>,
+    score: Res<Score>,
+    mut displayed: ResMut<DisplayedScore>,
+    time: Res<Time>,
+) {
+    let target = score.0 as f32;
+
+    if score.is_changed() {
+        if target < displayed.value {
+            // 重置分数时直接归零
+            displayed.value = target;
+            displayed.from = target;
+        } else {
+            displayed.from = displayed.value;
+        }
+        displayed.timer.reset();
+    } else if displayed.value == target {
         return;
     }
+
+    displayed.timer.tick(time.delta());
+    displayed.value = displayed.from + (target - displayed.from) * displayed.timer.percent();
+
     for mut text in query.iter_mut() {
         // 查询文本ui 显示分数
-        text.sections[1].value = format!("{}", score.0);
+        text.sections[1].value = format!("{}", displayed.value.round());
+    }
+}
+
+// 更新 WPM 和正确率，只在游戏中显示
+fn update_stats_text(
+    mut query: Query<(&mut Text, &mut Visibility), With<StatsText>>,
+    wpm: Res<Wpm>,
+    accuracy: Res<Accuracy>,
+    state: Res<State<AppState>>,
+    locale: Res<Locale>,
+) {
+    let playing = *state.current() == AppState::Playing;
+
+    for (mut text, mut visibility) in query.iter_mut() {
+        if visibility.is_visible != playing {
+            visibility.is_visible = playing;
+        }
+        if !playing {
+            continue;
+        }
+
+        let value = format!(
+            "{} {:.0}  {} {:.0}%",
+            locale.get("wpm"),
+            wpm.0,
+            locale.get("accuracy"),
+            accuracy.0 * 100.
+        );
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// 输错时屏幕上的单词变红约 0.2 秒
+fn flash_mistyped(
+    mut commands: Commands,
+    mut events: EventReader<Action>,
+    mut query: Query<(Entity, &mut Text, Option<&mut Mistyped>), With<TypingTarget>>,
+    time: Res<Time>,
+) {
+    let mistyped = events.iter().any(|e| matches!(e, Action::BadFlap));
+
+    for (entity, mut text, flash) in query.iter_mut() {
+        match flash {
+            Some(mut flash) => {
+                if mistyped {
+                    flash.timer.reset();
+                }
+                flash.timer.tick(time.delta());
+                if flash.timer.finished() {
+                    for (section, color) in text.sections.iter_mut().zip(&flash.colors) {
+                        section.style.color = *color;
+                    }
+                    commands.entity(entity).remove::<Mistyped>();
+                }
+            }
+            None if mistyped => {
+                let colors = text.sections.iter().map(|s| s.style.color).collect();
+                for section in text.sections.iter_mut() {
+                    section.style.color = Color::RED;
+                }
+                commands.entity(entity).insert(Mistyped {
+                    timer: Timer::from_seconds(0.2, false),
+                    colors,
+                });
+            }
+            None => {}
+        }
     }
 }
 
+// 打字目标的三段文字：已输入的部分、下一个要输入的字母和剩下的部分
+// 由 update_targets 按输入的进度拆分，创建时整个单词都放在最后一段
+fn target_sections(
+    word: &str,
+    font: &Handle<Font>,
+    font_size: f32,
+    color: Color,
+) -> Vec<TextSection> {
+    let section = |value: &str, color: Color| TextSection {
+        value: value.into(),
+        style: TextStyle {
+            font: font.clone(),
+            font_size,
+            color,
+        },
+    };
+
+    vec![
+        section("", Color::GREEN),
+        section("", NEXT_LETTER_COLOR),
+        section(word, color),
+    ]
+}
+
+// 把单词拆成已输入的部分、下一个字母和剩下的部分
+// index 是字符的序号，换算成字节位置再拆分；输入完成后没有下一个字母，后两段为空
+fn split_target(word: &str, index: usize) -> (&str, &str, &str) {
+    let mut bounds = word.char_indices().map(|(i, _)| i).skip(index);
+    let next = bounds.next().unwrap_or(word.len());
+    let rest = bounds.next().unwrap_or(word.len());
+
+    (&word[..next], &word[next..rest], &word[rest..])
+}
+
 // 更新目标单词
 fn update_targets(
     query: Query<(Entity, &TypingTarget), Changed<TypingTarget>>,
@@ -304,16 +1744,251 @@ fn update_targets(
 ) {
     for (entity, target) in query.iter() {
         if let Ok(mut text) = text_query.get_mut(entity) {
-            let parts = target.word.split_at(target.index);
+            let (typed, next, rest) = split_target(&target.word, target.index);
+
+            text.sections[0].value = typed.to_uppercase();
+            text.sections[1].value = next.to_uppercase();
+            text.sections[2].value = rest.to_uppercase();
+        }
+    }
+}
+
+fn spawn_perf_overlay(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(5.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 24.,
+                    color: Color::rgba(0.8, 0.8, 0.8, 1.0),
+                },
+            ),
+            visibility: Visibility { is_visible: false },
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        })
+        .insert(PerfOverlay);
+}
+
+fn toggle_perf_overlay(
+    keyboard: Res<Input<KeyCode>>,
+    mut query: Query<&mut Visibility, With<PerfOverlay>>,
+) {
+    if !keyboard.just_pressed(PERF_OVERLAY_KEY) {
+        return;
+    }
+    for mut visibility in query.iter_mut() {
+        visibility.is_visible = !visibility.is_visible;
+    }
+}
+
+// 显示最近 20 帧的平均帧率、平均帧时间和最长帧时间，最长帧时间可以看出生成网格造成的卡顿
+// 隐藏时不做任何事；显示时在原来的字符串上改写，不会每帧重新分配
+fn update_perf_overlay(
+    diagnostics: Res<Diagnostics>,
+    mut query: Query<(&mut Text, &Visibility), With<PerfOverlay>>,
+) {
+    use std::fmt::Write;
+
+    for (mut text, visibility) in query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        let (fps, frame, max) = match (
+            diagnostics.get(FrameTimeDiagnosticsPlugin::FPS),
+            diagnostics.get(FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        ) {
+            (Some(fps), Some(frame)) => (
+                fps.average().unwrap_or(0.),
+                frame.average().unwrap_or(0.),
+                frame.values().copied().fold(0., f64::max),
+            ),
+            _ => continue,
+        };
+
+        let value = &mut text.sections[0].value;
+        value.clear();
+        let _ = write!(
+            value,
+            "FPS {:.0}  FRAME {:.1} ms  MAX {:.1} ms",
+            fps,
+            frame * 1000.,
+            max * 1000.
+        );
+    }
+}
+
+// 窗口大小对应的文字缩放比例，宽和高中更紧张的一边决定缩放
+fn window_fit(width: f32, height: f32) -> f32 {
+    (width / REFERENCE_WIDTH)
+        .min(height / REFERENCE_HEIGHT)
+        .clamp(MIN_WINDOW_FIT, 1.)
+}
+
+// 窗口大小变化时重新计算文字缩放比例
+// 布局本身是百分比和 flex 居中，由 bevy_ui 自动重新计算，这里只需要让文字跟着缩放
+fn fit_to_window(
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut fit: ResMut<WindowFit>,
+) {
+    // 第一次运行时按窗口的初始大小计算，之后一帧内可能收到多次，只使用最后一次的大小
+    let primary = windows.get_primary();
+    let mut size = primary
+        .filter(|_| fit.is_added())
+        .map(|w| (w.width(), w.height()));
+    let primary = primary.map(|w| w.id());
+    for e in resized.iter() {
+        if Some(e.id) == primary {
+            size = Some((e.width, e.height));
+        }
+    }
+
+    if let Some((width, height)) = size {
+        let next = window_fit(width, height);
+        if next != fit.0 {
+            fit.0 = next;
+        }
+    }
+}
+
+// 按 Settings 中的 font_scale 和窗口大小缩放文字
+// 新创建的文字记录原始大小后缩放；设置或窗口大小变化时所有文字按原始大小重新缩放
+fn apply_font_scale(
+    mut commands: Commands,
+    mut new_text_query: Query<(Entity, &mut Text), Without<BaseFontSize>>,
+    mut text_query: Query<(&mut Text, &BaseFontSize)>,
+    settings: Res<Settings>,
+    fit: Res<WindowFit>,
+) {
+    let scale = settings.font_scale * fit.0;
+
+    for (entity, mut text) in new_text_query.iter_mut() {
+        let base: Vec<f32> = text.sections.iter().map(|s| s.style.font_size).collect();
+        for (section, size) in text.sections.iter_mut().zip(&base) {
+            section.style.font_size = size * scale;
+        }
+        commands.entity(entity).insert(BaseFontSize(base));
+    }
+
+    if !settings.is_changed() && !fit.is_changed() {
+        return;
+    }
+
+    for (mut text, base) in text_query.iter_mut() {
+        for (section, size) in text.sections.iter_mut().zip(&base.0) {
+            section.style.font_size = size * scale;
+        }
+    }
+}
+
+// 为每个新的打字目标在单词下方添加一条进度条
+fn add_progress_bars(mut commands: Commands, query: Query<Entity, Added<TypingTarget>>) {
+    for entity in query.iter() {
+        let bar = commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.),
+                        bottom: Val::Px(0.),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(0.), Val::Px(3.)),
+                    ..Default::default()
+                },
+                color: Color::GREEN.into(),
+                ..Default::default()
+            })
+            .insert(ProgressBar)
+            .id();
+        commands.entity(entity).add_child(bar);
+    }
+}
+
+// 进度条宽度为已输入字母占整个单词的比例
+// 换新单词时 index 归零，进度条也回到 0%
+fn update_progress_bars(
+    query: Query<(&TypingTarget, &Children), Changed<TypingTarget>>,
+    mut bar_query: Query<&mut Style, With<ProgressBar>>,
+) {
+    for (target, children) in query.iter() {
+        let progress = target.progress();
+
+        for child in children.iter() {
+            if let Ok(mut style) = bar_query.get_mut(*child) {
+                style.size.width = Val::Percent(progress * 100.);
+            }
+        }
+    }
+}
+
+// 为游戏中的单词添加倒计时条，没有倒计时时宽度为 0
+fn add_timer_bars(
+    mut commands: Commands,
+    query: Query<(Entity, &TypingTarget), Added<TypingTarget>>,
+) {
+    for (entity, _) in query.iter().filter(|(_, t)| !t.is_whole()) {
+        let bar = commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.),
+                        top: Val::Px(0.),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(0.), Val::Px(3.)),
+                    ..Default::default()
+                },
+                color: Color::ORANGE.into(),
+                ..Default::default()
+            })
+            .insert(TimerBar)
+            .id();
+        commands.entity(entity).add_child(bar);
+    }
+}
 
-            text.sections[0].value = parts.0.to_uppercase();
-            text.sections[1].value = parts.1.to_uppercase();
+// 倒计时条宽度为剩余时间的比例
+fn update_timer_bars(
+    query: Query<(Option<&WordTimer>, &Children), With<TypingTarget>>,
+    mut bar_query: Query<&mut Style, With<TimerBar>>,
+) {
+    for (word_timer, children) in query.iter() {
+        let left = word_timer.map_or(0., |t| t.timer.percent_left());
+        let width = Val::Percent(left * 100.);
+
+        for child in children.iter() {
+            if let Ok(mut style) = bar_query.get_mut(*child) {
+                if style.size.width != width {
+                    style.size.width = width;
+                }
+            }
         }
     }
 }
 
 // 初始化上下文本框中显示的单词
-fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Res<FontAssets>) {
+fn setup(
+    mut commands: Commands,
+    mut wordlist: ResMut<WordList>,
+    layout: Res<KeyboardLayout>,
+    font_assets: Res<FontAssets>,
+    command_words: Res<CommandWords>,
+    locale: Res<Locale>,
+) {
     // root node
     let root = commands
         .spawn_bundle(NodeBundle {
@@ -331,7 +2006,9 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
     let topbar = commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Px(50.)),
+                // 高度随文字大小变化，最小 50 像素
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                min_size: Size::new(Val::Auto, Val::Px(50.)),
                 align_items: AlignItems::Center,
                 justify_content: JustifyContent::Center,
                 padding: UiRect {
@@ -345,8 +2022,8 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
         })
         .id();
 
-    let mut not: HashSet<char> = "start".chars().collect();
-    let topword = wordlist.find_next_word(&not);
+    let mut not: HashSet<char> = command_words.start.chars().collect();
+    let topword = wordlist.find_next_word(&not, &layout);
     for c in topword.chars() {
         not.insert(c);
     }
@@ -358,24 +2035,12 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
                 ..Default::default()
             },
             text: Text {
-                sections: vec![
-                    TextSection {
-                        value: "".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::GREEN,
-                        },
-                    },
-                    TextSection {
-                        value: topword.clone(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::rgb_u8(255, 235, 146),
-                        },
-                    },
-                ],
+                sections: target_sections(
+                    &topword,
+                    &font_assets.main,
+                    40.,
+                    Color::rgb_u8(255, 235, 146),
+                ),
                 ..Default::default()
             },
             ..Default::default()
@@ -389,7 +2054,9 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
     let bottombar = commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Px(50.)),
+                // 高度随文字大小变化，最小 50 像素
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                min_size: Size::new(Val::Auto, Val::Px(50.)),
                 align_items: AlignItems::Center,
                 justify_content: JustifyContent::Center,
                 padding: UiRect {
@@ -403,7 +2070,7 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
         })
         .id();
 
-    let bottomword = wordlist.find_next_word(&not);
+    let bottomword = wordlist.find_next_word(&not, &layout);
     let bottomtext = commands
         .spawn_bundle(TextBundle {
             style: Style {
@@ -411,24 +2078,12 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
                 ..Default::default()
             },
             text: Text {
-                sections: vec![
-                    TextSection {
-                        value: "".into(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::GREEN,
-                        },
-                    },
-                    TextSection {
-                        value: bottomword.clone(),
-                        style: TextStyle {
-                            font: font_assets.main.clone(),
-                            font_size: 40.,
-                            color: Color::rgb_u8(255, 235, 146),
-                        },
-                    },
-                ],
+                sections: target_sections(
+                    &bottomword,
+                    &font_assets.main,
+                    40.,
+                    Color::rgb_u8(255, 235, 146),
+                ),
                 ..Default::default()
             },
             ..Default::default()
@@ -454,7 +2109,7 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
             text: Text {
                 sections: vec![
                     TextSection {
-                        value: "SCORE ".into(),
+                        value: format!("{} ", locale.get("score")),
                         style: TextStyle {
                             font: font_assets.main.clone(),
                             font_size: 40.,
@@ -477,7 +2132,304 @@ fn setup(mut commands: Commands, mut wordlist: ResMut<WordList>, font_assets: Re
         .insert(ScoreText)
         .id();
 
+    // 放在与分数相对的右侧
+    let statstext = commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(3.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: UiRect::all(Val::Px(5.0)),
+                ..Default::default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font_assets.main.clone(),
+                    font_size: 30.,
+                    color: Color::rgba(0.8, 0.8, 0.8, 1.0),
+                },
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(StatsText)
+        .id();
+
     commands.entity(root).push_children(&[topbar, bottombar]);
-    commands.entity(topbar).push_children(&[toptext, scoretext]);
+    commands
+        .entity(topbar)
+        .push_children(&[toptext, scoretext, statstext]);
     commands.entity(bottombar).push_children(&[bottomtext]);
 }
+
+// 设置界面，覆盖在开始屏幕之上
+fn settings_screen(mut commands: Commands, font_assets: Res<FontAssets>, locale: Res<Locale>) {
+    let container = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.8).into(),
+            ..Default::default()
+        })
+        .insert(SettingsScreen)
+        .id();
+
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_assets.main.clone(),
+        font_size,
+        color,
+    };
+
+    let title = commands
+        .spawn_bundle(TextBundle::from_section(
+            locale.get("options"),
+            text_style(60., Color::WHITE),
+        ))
+        .id();
+    commands.entity(container).push_children(&[title]);
+
+    for row in 0..SETTINGS_ROWS {
+        let text = commands
+            .spawn_bundle(TextBundle::from_section("", text_style(40., Color::WHITE)))
+            .insert(SettingsRow(row))
+            .id();
+        commands.entity(container).push_children(&[text]);
+    }
+
+    let help = commands
+        .spawn_bundle(TextBundle::from_section(
+            locale.get("options_hint"),
+            text_style(30., Color::rgba(0.8, 0.8, 0.8, 1.0)),
+        ))
+        .id();
+    commands.entity(container).push_children(&[help]);
+}
+
+// 设置界面的输入：上下选择，左右或数字键调整，回车或 Esc 返回
+fn settings_input(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut selected: ResMut<SettingsSelection>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Up) {
+        selected.0 = (selected.0 + SETTINGS_ROWS - 1) % SETTINGS_ROWS;
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        selected.0 = (selected.0 + 1) % SETTINGS_ROWS;
+    }
+
+    if keyboard.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        keyboard.clear();
+        state.pop().unwrap();
+        return;
+    }
+
+    // 空隙预览：左右切换开关
+    if selected.0 == GAP_PREVIEW_ROW {
+        char_input_events.clear();
+
+        if keyboard.any_just_pressed([KeyCode::Left, KeyCode::Right]) {
+            settings.gap_preview = !settings.gap_preview;
+        }
+        return;
+    }
+
+    // 文字大小：左右调整
+    if selected.0 == TEXT_SIZE_ROW {
+        char_input_events.clear();
+
+        let mut scale = settings.font_scale;
+        if keyboard.just_pressed(KeyCode::Left) {
+            scale -= FONT_SCALE_STEP;
+        }
+        if keyboard.just_pressed(KeyCode::Right) {
+            scale += FONT_SCALE_STEP;
+        }
+        let scale = scale.clamp(FONT_SCALE_MIN, FONT_SCALE_MAX);
+        if (scale - settings.font_scale).abs() > f32::EPSILON {
+            settings.font_scale = scale;
+        }
+        return;
+    }
+
+    let volume = match selected.0 {
+        MUSIC_ROW => settings.audio.music_volume,
+        _ => settings.audio.sfx_volume,
+    };
+    let mut level = (volume * VOLUME_STEPS).round();
+
+    if keyboard.just_pressed(KeyCode::Left) {
+        level -= 1.;
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        level += 1.;
+    }
+    for event in char_input_events.iter() {
+        if let Some(digit) = event.char.to_digit(10) {
+            level = digit as f32;
+        }
+    }
+
+    // 只在数值变化时修改，避免每帧都触发 Settings 的变化检测
+    let new_volume = level.clamp(0., VOLUME_STEPS) / VOLUME_STEPS;
+    if (new_volume - volume).abs() > f32::EPSILON {
+        match selected.0 {
+            MUSIC_ROW => settings.audio.music_volume = new_volume,
+            _ => settings.audio.sfx_volume = new_volume,
+        }
+    }
+}
+
+fn volume_level(volume: f32) -> String {
+    ((volume * VOLUME_STEPS).round() as u32).to_string()
+}
+
+// 刷新设置界面每一行的文字，当前选中的行高亮
+fn update_settings_rows(
+    mut query: Query<(&mut Text, &SettingsRow)>,
+    settings: Res<Settings>,
+    selected: Res<SettingsSelection>,
+    locale: Res<Locale>,
+) {
+    for (mut text, row) in query.iter_mut() {
+        let (label, value) = match row.0 {
+            MUSIC_ROW => ("music", volume_level(settings.audio.music_volume)),
+            SFX_ROW => ("sfx", volume_level(settings.audio.sfx_volume)),
+            TEXT_SIZE_ROW => ("text_size", format!("{:.2}x", settings.font_scale)),
+            _ => (
+                "gap_preview",
+                locale
+                    .get(if settings.gap_preview { "on" } else { "off" })
+                    .to_string(),
+            ),
+        };
+        let value = format!("{} < {} >", locale.get(label), value);
+        let color = if row.0 == selected.0 {
+            Color::rgb_u8(255, 235, 146)
+        } else {
+            Color::WHITE
+        };
+
+        // 只在内容变化时修改，避免每帧都重新排版文字
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_splits_around_the_next_letter() {
+        assert_eq!(split_target("birb", 0), ("", "b", "irb"));
+        assert_eq!(split_target("birb", 3), ("bir", "b", ""));
+        assert_eq!(split_target("naïve", 2), ("na", "ï", "ve"));
+        // 输入完成后没有下一个字母
+        assert_eq!(split_target("birb", 4), ("birb", "", ""));
+        assert_eq!(split_target("birb", 9), ("birb", "", ""));
+    }
+
+    #[test]
+    fn death_message_combines_speed_and_accuracy() {
+        let locale = Locale::default();
+        let msg = |score, accuracy, wpm, beat| {
+            death_message(&locale, &Score(score), &Accuracy(accuracy), &Wpm(wpm), beat)
+        };
+
+        assert!(msg(50, 0.7, 80., false).starts_with("You were fast but sloppy!"));
+        assert!(msg(50, 1., 80., false).starts_with("Quick and clean!"));
+        assert!(msg(300, 1., 10., false).starts_with("Slow but steady!"));
+        // 超过竞争鸟时在评价前多一行，乱按键盘超过的另有说法
+        assert!(msg(50, 1., 80., true).starts_with("You beat the flock!\nQuick and clean!"));
+        assert!(msg(500, 0.5, 40., true).starts_with("You beat the flock...\n"));
+    }
+
+    #[test]
+    fn death_message_falls_back_to_score_tiers() {
+        let locale = Locale::default();
+        let msg = |score| death_message(&locale, &Score(score), &Accuracy(0.9), &Wpm(40.), false);
+
+        assert!(msg(0).starts_with("Oh wow, ouch!"));
+        assert!(msg(201).starts_with("Not bad, kid!"));
+        assert!(msg(401).starts_with("That was a close one!"));
+        assert!(msg(1001).starts_with("I... wha... wow!"));
+        // 正确率很高但速度一般，分数低时不算“稳”
+        let steady = death_message(&locale, &Score(10), &Accuracy(1.), &Wpm(10.), false);
+        assert!(steady.starts_with("Oh wow, ouch!"));
+    }
+
+    #[test]
+    fn default_window_keeps_text_size() {
+        assert_eq!(window_fit(REFERENCE_WIDTH, REFERENCE_HEIGHT), 1.);
+        assert_eq!(window_fit(2560., 1440.), 1.);
+    }
+
+    #[test]
+    fn wide_window_is_limited_by_height() {
+        assert_eq!(window_fit(3000., 540.), 0.75);
+    }
+
+    #[test]
+    fn tall_window_is_limited_by_width() {
+        assert_eq!(window_fit(960., 2000.), 0.75);
+        assert_eq!(window_fit(100., 2000.), MIN_WINDOW_FIT);
+    }
+
+    // 记录这一帧文字被修改过的行数
+    #[derive(Default)]
+    struct ChangedRows(usize);
+
+    fn count_changed_rows(query: Query<(), Changed<Text>>, mut changed: ResMut<ChangedRows>) {
+        changed.0 = query.iter().count();
+    }
+
+    #[test]
+    fn settings_rows_are_only_rewritten_when_they_change() {
+        let mut app = App::new();
+        app.init_resource::<Settings>()
+            .init_resource::<SettingsSelection>()
+            .init_resource::<Locale>()
+            .init_resource::<ChangedRows>()
+            .add_system(update_settings_rows.label("rows"))
+            .add_system(count_changed_rows.after("rows"));
+        let row = app
+            .world
+            .spawn()
+            .insert(Text::from_section("", TextStyle::default()))
+            .insert(SettingsRow(SFX_ROW))
+            .id();
+        let changed = |app: &mut App| {
+            app.update();
+            app.world.resource::<ChangedRows>().0 == 1
+        };
+
+        assert!(changed(&mut app));
+        let text = &app.world.get::<Text>(row).unwrap().sections[0];
+        assert_eq!(text.style.color, Color::WHITE);
+        // 数值和选中的行都没变时不改文字
+        assert!(!changed(&mut app));
+
+        app.world.resource_mut::<SettingsSelection>().0 = SFX_ROW;
+        assert!(changed(&mut app));
+        let text = &app.world.get::<Text>(row).unwrap().sections[0];
+        assert_eq!(text.style.color, Color::rgb_u8(255, 235, 146));
+        assert!(!changed(&mut app));
+    }
+}
@@ -1,8 +1,13 @@
-use bevy::render::primitives::Aabb;
+use bevy::{math::Vec3, render::primitives::Aabb};
 
 // 图示参考：https://developer.mozilla.org/zh-CN/docs/Games/Techniques/3D_collision_detection
 // 具体的碰撞检测算法
 pub fn collide_aabb(a: &Aabb, b: &Aabb) -> bool {
+    aabb_overlap(a, b).is_some()
+}
+
+// 两个碰撞盒在每个轴上的重叠深度，不重叠时返回 None
+pub fn aabb_overlap(a: &Aabb, b: &Aabb) -> Option<Vec3> {
     // a 的最小值指 左上角xy坐标
     let a_min = a.min();
     // a 的最大值指 右下角xy坐标
@@ -17,10 +22,141 @@ pub fn collide_aabb(a: &Aabb, b: &Aabb) -> bool {
     // - 物体B的X轴方向最小值大于物体A的X轴方向最大值；
     // 若满足上述条件，则证明物体A与物体B并未发生重合
     // 反之，则证明物体A与物体B重合。
-    a_max.x > b_min.x
-        && a_min.x < b_max.x
-        && a_max.y > b_min.y
-        && a_min.y < b_max.y
-        && a_max.z > b_min.z
-        && a_min.z < b_max.z
+    // 每个轴上重叠的长度为两者最大值中较小的减去最小值中较大的，只是贴在一起（长度为 0）不算重合
+    let overlap = Vec3::from(a_max.min(b_max) - a_min.max(b_min));
+
+    if overlap.cmpgt(Vec3::ZERO).all() {
+        Some(overlap)
+    } else {
+        None
+    }
+}
+
+// 扫掠碰撞检测：a_prev 为上一帧的 a（已换算到 b 的参考系）
+// 速度较快时 a 在一帧内可能直接穿过较薄的 b，单看当前帧的重叠会漏判
+// 做法是把 b 按 a 的半长扩大（闵可夫斯基和），再判断 a 的中心在这一帧走过的线段是否穿过扩大后的 b
+pub fn swept_collide_aabb(a: &Aabb, a_prev: &Aabb, b: &Aabb) -> bool {
+    if collide_aabb(a, b) || collide_aabb(a_prev, b) {
+        return true;
+    }
+
+    let half_extents = b.half_extents + a.half_extents;
+    let min = b.center - half_extents;
+    let max = b.center + half_extents;
+
+    let start = a_prev.center;
+    let dir = a.center - a_prev.center;
+
+    let mut t_enter = 0f32;
+    let mut t_exit = 1f32;
+
+    for axis in 0..3 {
+        if dir[axis].abs() <= f32::EPSILON {
+            // 该轴上没有移动，必须始终处于 b 的范围内
+            if start[axis] <= min[axis] || start[axis] >= max[axis] {
+                return false;
+            }
+            continue;
+        }
+
+        let t0 = (min[axis] - start[axis]) / dir[axis];
+        let t1 = (max[axis] - start[axis]) / dir[axis];
+
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+
+        if t_enter >= t_exit {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Vec3A;
+
+    fn aabb(center: Vec3A, half_extents: Vec3A) -> Aabb {
+        Aabb {
+            center,
+            half_extents,
+        }
+    }
+
+    #[test]
+    fn touching_edges_do_not_collide() {
+        let half = Vec3A::splat(0.5);
+        let a = aabb(Vec3A::ZERO, half);
+        let b = aabb(Vec3A::new(1., 0., 0.), half);
+
+        assert_eq!(aabb_overlap(&a, &b), None);
+        assert!(!collide_aabb(&a, &b));
+    }
+
+    #[test]
+    fn nested_overlap_is_inner_size() {
+        let outer = aabb(Vec3A::ZERO, Vec3A::splat(2.));
+        let inner = aabb(Vec3A::new(0.5, 0., 0.), Vec3A::new(0.5, 0.25, 1.));
+
+        let expected = Vec3::new(1., 0.5, 2.);
+        assert_eq!(aabb_overlap(&outer, &inner), Some(expected));
+        assert_eq!(aabb_overlap(&inner, &outer), Some(expected));
+        assert!(collide_aabb(&outer, &inner));
+    }
+
+    #[test]
+    fn partial_overlap() {
+        let half = Vec3A::splat(0.5);
+        let a = aabb(Vec3A::ZERO, half);
+        let b = aabb(Vec3A::new(0.75, 0.5, 0.), half);
+
+        assert_eq!(aabb_overlap(&a, &b), Some(Vec3::new(0.25, 0.5, 1.)));
+        assert!(collide_aabb(&a, &b));
+    }
+
+    #[test]
+    fn swept_catches_tunneling() {
+        let half = Vec3A::new(0.2, 0.3, 0.25);
+        let prev = aabb(Vec3A::new(-2., 1., 0.), half);
+        let current = aabb(Vec3A::new(2., 1., 0.), half);
+        // 很薄的静止障碍物，位于起点和终点之间
+        let wall = aabb(Vec3A::new(0., 1., 0.), Vec3A::new(0.05, 1., 1.));
+
+        assert!(!collide_aabb(&prev, &wall));
+        assert!(!collide_aabb(&current, &wall));
+        assert!(swept_collide_aabb(&current, &prev, &wall));
+    }
+
+    #[test]
+    fn mirrored_scene_collides_the_same() {
+        // 镜像模式只是把 x 坐标取反，碰撞检测的结果应该不变
+        let mirror = |a: &Aabb| aabb(a.center * Vec3A::new(-1., 1., 1.), a.half_extents);
+        let half = Vec3A::new(0.2, 0.3, 0.25);
+        let wall = aabb(Vec3A::new(0.5, 1., 0.), Vec3A::new(0.05, 1., 1.));
+        for (prev, current) in [(-2., 2.), (-2., 0.3), (0.3, 2.), (1., 3.)] {
+            let prev = aabb(Vec3A::new(prev, 1., 0.), half);
+            let current = aabb(Vec3A::new(current, 1., 0.), half);
+
+            assert_eq!(
+                collide_aabb(&current, &wall),
+                collide_aabb(&mirror(&current), &mirror(&wall))
+            );
+            assert_eq!(
+                swept_collide_aabb(&current, &prev, &wall),
+                swept_collide_aabb(&mirror(&current), &mirror(&prev), &mirror(&wall))
+            );
+        }
+    }
+
+    #[test]
+    fn swept_misses_when_path_is_clear() {
+        let half = Vec3A::new(0.2, 0.3, 0.25);
+        let prev = aabb(Vec3A::new(-2., 5., 0.), half);
+        let current = aabb(Vec3A::new(2., 5., 0.), half);
+        let wall = aabb(Vec3A::new(0., 1., 0.), Vec3A::new(0.05, 1., 1.));
+
+        assert!(!swept_collide_aabb(&current, &prev, &wall));
+    }
 }
@@ -1,3 +1,12 @@
+// 单词分类，在开始屏幕上输入分类名选择，默认为 classic
+pub const CATEGORIES: [(&str, &str); 4] = [
+    ("classic", WORDS),
+    ("animals", ANIMALS),
+    ("tech", TECH),
+    ("food", FOOD),
+];
+
+// 默认分类：五个字母的常用单词
 pub const WORDS: &str = "
 cigar
 rebut
@@ -2422,3 +2431,174 @@ looms
 lotus
 lurks
 ";
+
+// 动物
+pub const ANIMALS: &str = "
+cat
+dog
+owl
+fox
+bee
+elk
+yak
+emu
+ram
+eel
+ant
+bat
+cow
+pig
+hen
+jay
+cod
+gnu
+ape
+boar
+crab
+deer
+duck
+frog
+goat
+hawk
+lamb
+lion
+mole
+moth
+mule
+newt
+puma
+seal
+slug
+swan
+toad
+wolf
+wren
+crow
+lynx
+zebra
+tiger
+otter
+panda
+koala
+camel
+moose
+horse
+sheep
+snake
+whale
+shark
+squid
+skunk
+llama
+hippo
+bison
+finch
+robin
+";
+
+// 科技
+pub const TECH: &str = "
+byte
+code
+data
+disk
+file
+node
+port
+ram
+rom
+bug
+bit
+app
+web
+wifi
+chip
+cpu
+gpu
+link
+loop
+mouse
+pixel
+stack
+queue
+cache
+login
+email
+cloud
+debug
+linux
+shell
+emoji
+modem
+laser
+robot
+patch
+array
+query
+token
+hash
+java
+rust
+perl
+ruby
+kernel
+server
+socket
+router
+script
+driver
+";
+
+// 食物
+pub const FOOD: &str = "
+egg
+jam
+pie
+tea
+ham
+fig
+nut
+oat
+rice
+soup
+cake
+bun
+corn
+kale
+leek
+lime
+pear
+plum
+taco
+tofu
+apple
+bread
+candy
+curry
+grape
+honey
+lemon
+mango
+melon
+olive
+onion
+pasta
+peach
+pizza
+salad
+sushi
+toast
+bagel
+chili
+cocoa
+crepe
+donut
+gravy
+nacho
+prawn
+steak
+waffle
+cheese
+cookie
+noodle
+";